@@ -0,0 +1,278 @@
+use std::hint::black_box;
+
+use sheps_chunkerino::{
+    double_chunk, layout, par, pool::PartsPool, precomputed, schedule::Schedule, seri, seri2,
+    seri2_flipped, seri2_slice, seri2_slice_flipped, split,
+};
+
+use criterion::{
+    criterion_group, criterion_main, measurement::WallTime, BenchmarkGroup, BenchmarkId, Criterion,
+};
+
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let points = [
+        // (1, 1),
+        (100_000, 1),
+        (100_000, 2),
+        (100_000, 3),
+        (100_000, 4),
+        (100_000, 5),
+        (100_000, 6),
+        (100_000, 7),
+        (100_000, 8),
+        (100_000, 9),
+        (100_000, 10),
+        (100_000, 11),
+        (100_000, 12),
+        (100_000, 13),
+        (100_000, 14),
+        (100_000, 15),
+        (100_000, 16),
+        (100_000, 17),
+        (100_000, 18),
+        (100_000, 19),
+        (100_000, 20),
+        (100_000, 31),
+        (100_000, 32),
+        (100_000, 33),
+        (100_000, 63),
+        (100_000, 64),
+        (100_000, 65),
+        (100_000, 127),
+        (100_000, 128),
+        (100_000, 129),
+        // (100_000, 100_000),
+        // (10, 32),
+        // (100, 32),
+        // (1_000, 32),
+        // (10_000, 32),
+        // (1_000_000, 32),
+        // (10_000_000, 32),
+        // (100_000, 3),
+        // (100_000, 32),
+        // (100_000, 320),
+        // (100_000, 3200),
+        // (100_000, 32000),
+        // (100_000, 320000),
+    ];
+
+    let data = points.map(|(l, n)| (vec![0u8; l], n, format!("len={l},n_chunks={n:06}")));
+
+    let mut group = c.benchmark_group("Parts");
+
+    for (data, n, label) in data {
+        // group.bench_with_input(
+        //     BenchmarkId::new("on_demand", &label),
+        //     &(&data, n),
+        //     |b, (data, n)| b.iter(|| on_demand::Parts::new(data, *n).map(black_box).count()),
+        // );
+
+        group.bench_with_input(
+            BenchmarkId::new("precomputed", &label),
+            &(&data, n),
+            |b, (data, n)| b.iter(|| precomputed::Parts::new(data, *n).map(black_box).count()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("double_chunk", &label),
+            &(&data, n),
+            |b, (data, n)| b.iter(|| double_chunk::Parts::new(data, *n).map(black_box).count()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("seri", &label),
+            &(&data, n),
+            |b, (data, n)| b.iter(|| seri::Parts::new(data, *n).map(black_box).count()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("seri2", &label),
+            &(&data, n),
+            |b, (data, n)| b.iter(|| seri2::Parts::new(data, *n).map(black_box).count()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("seri2_flipped", &label),
+            &(&data, n),
+            |b, (data, n)| b.iter(|| seri2_flipped::Parts::new(data, *n).map(black_box).count()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("seri2_slice", &label),
+            &(&data, n),
+            |b, (data, n)| b.iter(|| seri2_slice::Parts::new(data, *n).map(black_box).count()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("seri2_slice_flipped", &label),
+            &(&data, n),
+            |b, (data, n)| {
+                b.iter(|| {
+                    seri2_slice_flipped::Parts::new(data, *n)
+                        .map(black_box)
+                        .count()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+/// Splits `slice` into `n` parts of the `seri2_slice` layout with
+/// `split`, returning the number of parts.
+#[inline(always)]
+fn split_parts<T, F>(mut slice: &[T], n: usize, split: F) -> usize
+where
+    F: for<'a> Fn(&'a [T], usize) -> (&'a [T], &'a [T]),
+{
+    let layout = layout::PartsLayout::new(slice.len(), n);
+    for i in 0..layout.n_parts() {
+        let (head, tail) = split(slice, layout.part_len(i));
+        black_box(head);
+        slice = tail;
+    }
+    layout.n_parts()
+}
+
+/// Compares the pointer-based `split_at_unchecked` against core's, in
+/// the same build, by splitting a slice into parts with each.
+pub fn split_benchmark(c: &mut Criterion) {
+    let data = vec![0u8; 100_000];
+
+    let mut group = c.benchmark_group("split_at_unchecked");
+
+    for n in [1, 7, 64, 1000, 100_000] {
+        let label = format!("len={},n_chunks={n:06}", data.len());
+
+        group.bench_with_input(BenchmarkId::new("ptr", &label), &n, |b, &n| {
+            b.iter(|| {
+                split_parts(&data, n, |s, mid| unsafe {
+                    split::split_at_unchecked_ptr(s, mid)
+                })
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("core", &label), &n, |b, &n| {
+            b.iter(|| split_parts(&data, n, |s, mid| unsafe { s.split_at_unchecked(mid) }))
+        });
+    }
+
+    group.finish();
+}
+
+/// Runs every variant over `data`, naming the results after the
+/// variant and `ty`.
+fn bench_variants<T: Sync>(
+    group: &mut BenchmarkGroup<'_, WallTime>,
+    ty: &str,
+    data: &[T],
+    n: usize,
+    label: &str,
+) {
+    let id = |variant: &str| BenchmarkId::new(format!("{variant}/{ty}"), label);
+
+    group.bench_function(id("precomputed"), |b| {
+        b.iter(|| precomputed::Parts::new(data, n).map(black_box).count())
+    });
+    group.bench_function(id("double_chunk"), |b| {
+        b.iter(|| double_chunk::Parts::new(data, n).map(black_box).count())
+    });
+    group.bench_function(id("seri"), |b| {
+        b.iter(|| seri::Parts::new(data, n).map(black_box).count())
+    });
+    group.bench_function(id("seri2"), |b| {
+        b.iter(|| seri2::Parts::new(data, n).map(black_box).count())
+    });
+    group.bench_function(id("seri2_flipped"), |b| {
+        b.iter(|| seri2_flipped::Parts::new(data, n).map(black_box).count())
+    });
+    group.bench_function(id("seri2_slice"), |b| {
+        b.iter(|| seri2_slice::Parts::new(data, n).map(black_box).count())
+    });
+    group.bench_function(id("seri2_slice_flipped"), |b| {
+        b.iter(|| {
+            seri2_slice_flipped::Parts::new(data, n)
+                .map(black_box)
+                .count()
+        })
+    });
+}
+
+/// ZST slices against `u8` slices of the same length, to check that
+/// ZSTs cost no more than real data, and ZST slices far longer than
+/// any real allocation.
+pub fn zst_benchmark(c: &mut Criterion) {
+    let zsts = [(); usize::MAX];
+    let bytes = vec![0u8; 100_000];
+
+    let mut group = c.benchmark_group("Parts<()>");
+
+    for n in [7, 128] {
+        let label = format!("len={},n_chunks={n:06}", bytes.len());
+        bench_variants(&mut group, "()", &zsts[..bytes.len()], n, &label);
+        bench_variants(&mut group, "u8", &bytes, n, &label);
+    }
+    for n in [1, 7, 128] {
+        let label = format!("len={},n_chunks={n:06}", usize::MAX);
+        bench_variants(&mut group, "()", &zsts, n, &label);
+    }
+
+    group.finish();
+}
+
+pub fn pool_benchmark(c: &mut Criterion) {
+    let data: Vec<u64> = (0..100_000).collect();
+    let mut group = c.benchmark_group("PartsPool");
+
+    for n in [2, 4, 8] {
+        group.bench_with_input(BenchmarkId::new("scoped", n), &n, |b, &n| {
+            b.iter(|| par::map_parts(&data, n, |_, part| part.iter().sum::<u64>()))
+        });
+        let mut pool = PartsPool::new(n);
+        group.bench_with_input(BenchmarkId::new("pool", n), &n, |b, _| {
+            b.iter(|| pool.map_parts(&data, |_, part| part.iter().sum::<u64>()))
+        });
+    }
+
+    group.finish();
+}
+
+/// Work that grows with the index, so the last parts cost far more
+/// than the first.
+fn skewed_work(i: usize) -> u64 {
+    (0..i / 64).fold(i as u64, |acc, x| {
+        black_box(acc.wrapping_mul(31) ^ x as u64)
+    })
+}
+
+pub fn schedule_benchmark(c: &mut Criterion) {
+    let len = 20_000;
+    let n_workers = 4;
+    let mut group = c.benchmark_group("Schedule");
+
+    for schedule in [
+        Schedule::Static,
+        Schedule::StaticChunked(64),
+        Schedule::Dynamic(64),
+        Schedule::Guided(16),
+    ] {
+        group.bench_function(format!("{schedule:?}"), |b| {
+            b.iter(|| {
+                let claimer = schedule.claimer(len, n_workers);
+                std::thread::scope(|s| {
+                    for w in 1..n_workers {
+                        let claimer = &claimer;
+                        s.spawn(move || claimer.worker(w).flatten().map(skewed_work).sum::<u64>());
+                    }
+                    claimer.worker(0).flatten().map(skewed_work).sum::<u64>()
+                })
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    criterion_benchmark,
+    split_benchmark,
+    zst_benchmark,
+    pool_benchmark,
+    schedule_benchmark
+);
+criterion_main!(benches);
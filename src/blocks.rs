@@ -0,0 +1,435 @@
+//! Block decomposition of an N-dimensional index space over a Cartesian
+//! grid of workers, as used for domain decomposition in simulations.
+//!
+//! Ranks are laid out in row-major order over the worker grid, like
+//! `MPI_Cart_create` does, and each axis is split with the balanced
+//! layout of `seri2_slice::Parts`.
+
+use core::{array, ops::Range};
+
+use crate::layout::PartsLayout;
+
+/// A `D`-dimensional index space split into one block per rank.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockDecomposition<const D: usize> {
+    /// Workers along each axis. Their product is the number of ranks.
+    grid: [usize; D],
+    axes: [PartsLayout; D],
+}
+
+impl<const D: usize> BlockDecomposition<D> {
+    /// Splits the index space `0..dims[0]` x `0..dims[1]` x ... into
+    /// `n_ranks` blocks.
+    ///
+    /// Out of every way to factor `n_ranks` into a worker grid, this
+    /// picks the one with the least total block surface, which also
+    /// minimises the surface-to-volume ratio. Grids that would leave
+    /// some ranks without elements are only picked if there is no
+    /// other choice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `D` or `n_ranks` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sheps_chunkerino::blocks::BlockDecomposition;
+    ///
+    /// let blocks = BlockDecomposition::new([100, 50], 8);
+    /// assert_eq!(blocks.grid(), [4, 2]);
+    /// assert_eq!(blocks.block(3), [25..50, 25..50]);
+    /// assert_eq!(blocks.owner_of([30, 40]), 3);
+    /// assert_eq!(blocks.neighbor(3, 0, 1), Some(5));
+    /// assert_eq!(blocks.neighbor(3, 1, 1), None);
+    /// ```
+    pub fn new(dims: [usize; D], n_ranks: usize) -> Self {
+        assert!(D > 0, "no dimensions to decompose");
+        assert!(n_ranks > 0, "no ranks to decompose over");
+
+        let mut search = GridSearch {
+            dims,
+            factors: Factors::of(n_ranks),
+            grid: [1; D],
+            best: None,
+        };
+        search.run(0, search.factors.exponents);
+        let (_, grid) = search.best.expect("n_ranks has at least one factorisation");
+
+        Self {
+            grid,
+            axes: array::from_fn(|axis| PartsLayout::new(dims[axis], grid[axis])),
+        }
+    }
+
+    /// The length of the index space along each axis.
+    #[inline]
+    pub fn dims(&self) -> [usize; D] {
+        array::from_fn(|axis| self.axes[axis].len())
+    }
+
+    /// The number of workers along each axis.
+    #[inline]
+    pub fn grid(&self) -> [usize; D] {
+        self.grid
+    }
+
+    /// The number of ranks, which is the product of [`grid`](Self::grid).
+    #[inline]
+    pub fn n_ranks(&self) -> usize {
+        self.grid.iter().product()
+    }
+
+    /// The position of `rank` in the worker grid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rank >= self.n_ranks()`.
+    pub fn grid_coords(&self, rank: usize) -> [usize; D] {
+        assert!(rank < self.n_ranks(), "rank out of range");
+
+        let mut rest = rank;
+        let mut coords = [0; D];
+        for axis in (0..D).rev() {
+            coords[axis] = rest % self.grid[axis];
+            rest /= self.grid[axis];
+        }
+        coords
+    }
+
+    /// The rank at `grid_coords` in the worker grid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `grid_coords` is outside the worker grid.
+    pub fn rank_at(&self, grid_coords: [usize; D]) -> usize {
+        grid_coords
+            .iter()
+            .zip(&self.grid)
+            .fold(0, |rank, (&c, &n)| {
+                assert!(c < n, "grid coordinates out of range");
+                rank * n + c
+            })
+    }
+
+    /// The ranges of indices along each axis that `rank` owns.
+    ///
+    /// A rank only ends up with an empty block if some axis is shorter
+    /// than the number of workers along it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rank >= self.n_ranks()`.
+    pub fn block(&self, rank: usize) -> [Range<usize>; D] {
+        let coords = self.grid_coords(rank);
+
+        array::from_fn(|axis| {
+            let layout = &self.axes[axis];
+            if coords[axis] < layout.n_parts() {
+                layout.part_range(coords[axis])
+            } else {
+                layout.len()..layout.len()
+            }
+        })
+    }
+
+    /// The rank owning the element at `coords`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `coords` is outside the index space.
+    pub fn owner_of(&self, coords: [usize; D]) -> usize {
+        self.rank_at(array::from_fn(|axis| self.axes[axis].part_of(coords[axis])))
+    }
+
+    /// The rank `offset` steps away from `rank` along `axis`, if it is
+    /// within the worker grid. The grid does not wrap around.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rank >= self.n_ranks()` or `axis >= D`.
+    pub fn neighbor(&self, rank: usize, axis: usize, offset: isize) -> Option<usize> {
+        let mut coords = self.grid_coords(rank);
+        coords[axis] = coords[axis]
+            .checked_add_signed(offset)
+            .filter(|&c| c < self.grid[axis])?;
+
+        Some(self.rank_at(coords))
+    }
+
+    /// The ranks sharing a face with `rank`, one step away along a
+    /// single axis, lower neighbour first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rank >= self.n_ranks()`.
+    pub fn neighbors(&self, rank: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..D).flat_map(move |axis| {
+            [-1, 1]
+                .into_iter()
+                .filter_map(move |offset| self.neighbor(rank, axis, offset))
+        })
+    }
+}
+
+/// The most distinct primes a `usize` can have. The product of the
+/// first 16 primes is more than `u64::MAX`.
+const MAX_PRIMES: usize = 15;
+
+/// The prime factorisation of a number, as distinct primes and their
+/// exponents.
+#[derive(Clone, Copy)]
+struct Factors {
+    primes: [usize; MAX_PRIMES],
+    exponents: [u32; MAX_PRIMES],
+    len: usize,
+}
+
+impl Factors {
+    /// Factors `n` by trial division, which takes up to `sqrt(n)` steps
+    /// once, however many axes the search goes through later.
+    fn of(mut n: usize) -> Self {
+        let mut factors = Self {
+            primes: [0; MAX_PRIMES],
+            exponents: [0; MAX_PRIMES],
+            len: 0,
+        };
+
+        let mut p = 2;
+        // Not `p * p <= n`, which overflows for `n` near `usize::MAX`.
+        while p <= n / p {
+            if n.is_multiple_of(p) {
+                let mut exponent = 0;
+                while n.is_multiple_of(p) {
+                    n /= p;
+                    exponent += 1;
+                }
+                factors.push(p, exponent);
+            }
+            p += if p == 2 { 1 } else { 2 };
+        }
+        if n > 1 {
+            factors.push(n, 1);
+        }
+
+        factors
+    }
+
+    fn push(&mut self, prime: usize, exponent: u32) {
+        self.primes[self.len] = prime;
+        self.exponents[self.len] = exponent;
+        self.len += 1;
+    }
+
+    /// The number with the given exponents for these primes.
+    fn product(&self, exponents: &[u32; MAX_PRIMES]) -> usize {
+        (0..self.len)
+            .map(|i| self.primes[i].pow(exponents[i]))
+            .product()
+    }
+}
+
+/// Exhaustive search over the ways to factor the number of ranks into
+/// a worker grid. The number is factored once, and the search only
+/// walks its divisors, of which there are at most about 10^5 for any
+/// `usize`.
+struct GridSearch<const D: usize> {
+    dims: [usize; D],
+    factors: Factors,
+    grid: [usize; D],
+    best: Option<((usize, f64), [usize; D])>,
+}
+
+impl<const D: usize> GridSearch<D> {
+    /// Tries every grid whose axes from `axis` on multiply to the
+    /// number with exponents `left`.
+    fn run(&mut self, axis: usize, left: [u32; MAX_PRIMES]) {
+        if axis == D - 1 {
+            self.grid[axis] = self.factors.product(&left);
+            self.consider();
+            return;
+        }
+
+        self.pick(axis, 0, 1, left);
+    }
+
+    /// Picks how often `factors.primes[prime]` and the primes after it
+    /// divide `grid[axis]`, which is `size` so far.
+    fn pick(&mut self, axis: usize, prime: usize, size: usize, mut left: [u32; MAX_PRIMES]) {
+        if prime == self.factors.len {
+            self.grid[axis] = size;
+            self.run(axis + 1, left);
+            return;
+        }
+
+        let p = self.factors.primes[prime];
+        let available = left[prime];
+        let mut size = size;
+        for taken in 0..=available {
+            left[prime] = available - taken;
+            self.pick(axis, prime + 1, size, left);
+            if taken < available {
+                size *= p;
+            }
+        }
+    }
+
+    fn consider(&mut self) {
+        // Ranks left without elements, then the block surface. Block
+        // `i` is `dims[i] / grid[i]` long, so up to a constant factor
+        // its surface is the sum of `grid[i] / dims[i]`.
+        let busy: usize = (0..D).map(|i| self.grid[i].min(self.dims[i])).product();
+        let idle = self.grid.iter().product::<usize>() - busy;
+        let surface: f64 = (0..D)
+            .map(|i| self.grid[i] as f64 / self.dims[i].max(1) as f64)
+            .sum();
+
+        let better = match self.best {
+            None => true,
+            Some(((best_idle, best_surface), best_grid)) => {
+                // Surfaces that only differ by rounding are ties, which
+                // go to grids with more workers along the earlier axes.
+                let tolerance = best_surface * 1e-9;
+                idle < best_idle
+                    || idle == best_idle
+                        && (surface < best_surface - tolerance
+                            || surface <= best_surface + tolerance && self.grid > best_grid)
+            }
+        };
+        if better {
+            self.best = Some(((idle, surface), self.grid));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{vec, vec::Vec};
+
+    /// Checks that every element has exactly one owner and that the
+    /// blocks agree with `owner_of`.
+    fn check_cover<const D: usize>(blocks: &BlockDecomposition<D>) {
+        let dims = blocks.dims();
+        let total: usize = dims.iter().product();
+        let mut owners = vec![usize::MAX; total];
+
+        for rank in 0..blocks.n_ranks() {
+            assert_eq!(blocks.rank_at(blocks.grid_coords(rank)), rank);
+
+            let block = blocks.block(rank);
+            let mut coords = array::from_fn(|axis| block[axis].start);
+            if block.iter().any(|r| r.is_empty()) {
+                continue;
+            }
+            'walk: loop {
+                let flat = coords.iter().zip(&dims).fold(0, |f, (&c, &d)| f * d + c);
+                assert_eq!(owners[flat], usize::MAX, "{coords:?} owned twice");
+                owners[flat] = rank;
+                assert_eq!(blocks.owner_of(coords), rank, "{coords:?}");
+
+                for axis in (0..D).rev() {
+                    coords[axis] += 1;
+                    if coords[axis] < block[axis].end {
+                        continue 'walk;
+                    }
+                    coords[axis] = block[axis].start;
+                }
+                break;
+            }
+        }
+
+        assert!(owners.iter().all(|&o| o != usize::MAX), "unowned elements");
+    }
+
+    #[test]
+    fn blocks_cover_the_space() {
+        for p in 1..=24 {
+            for dims in [[1, 1, 1], [7, 5, 3], [12, 12, 12], [30, 2, 9], [0, 4, 4]] {
+                let blocks = BlockDecomposition::new(dims, p);
+                assert_eq!(blocks.n_ranks(), p, "{dims:?}, p={p}");
+                check_cover(&blocks);
+            }
+            check_cover(&BlockDecomposition::new([37, 11], p));
+            check_cover(&BlockDecomposition::new([37], p));
+        }
+    }
+
+    #[test]
+    fn grid_follows_the_shape() {
+        assert_eq!(BlockDecomposition::new([64, 64, 64], 8).grid(), [2, 2, 2]);
+        assert_eq!(BlockDecomposition::new([64, 64, 64], 12).grid(), [3, 2, 2]);
+        assert_eq!(BlockDecomposition::new([256, 128, 64], 8).grid(), [4, 2, 1]);
+        assert_eq!(BlockDecomposition::new([10, 1000], 10).grid(), [1, 10]);
+        assert_eq!(BlockDecomposition::new([10, 10], 7).grid(), [7, 1]);
+        // Ties go to the earlier axes, even where the surfaces of the
+        // tied grids round differently.
+        assert_eq!(BlockDecomposition::new([12, 12, 12], 7).grid(), [7, 1, 1]);
+        assert_eq!(BlockDecomposition::new([3, 3, 3], 9).grid(), [3, 3, 1]);
+        // Only 3 workers fit along each axis.
+        assert_eq!(BlockDecomposition::new([3, 3], 9).grid(), [3, 3]);
+        assert_eq!(BlockDecomposition::new([3, 3], 4).grid(), [2, 2]);
+    }
+
+    #[test]
+    fn idle_ranks_when_unavoidable() {
+        let blocks = BlockDecomposition::new([2, 2], 5);
+        assert_eq!(blocks.grid(), [5, 1]);
+        assert_eq!(blocks.block(1), [1..2, 0..2]);
+        assert_eq!(blocks.block(4), [2..2, 0..2]);
+        check_cover(&blocks);
+    }
+
+    #[test]
+    fn large_rank_counts_are_fast() {
+        use std::time::{Duration, Instant};
+
+        let start = Instant::now();
+
+        let blocks = BlockDecomposition::new([1 << 22; 3], 1 << 60);
+        assert_eq!(blocks.grid(), [1 << 20; 3]);
+
+        // Two primes, so the only grids put them on separate axes or
+        // together.
+        let (p, q) = (1_000_003, 4_294_967_291);
+        let blocks = BlockDecomposition::new([1 << 40, 1 << 40, 1], p * q);
+        assert_eq!(blocks.grid(), [q, p, 1]);
+
+        // Highly composite, with many grids to try.
+        let n = 735_134_400;
+        let blocks = BlockDecomposition::new([10_000; 3], n);
+        assert_eq!(blocks.n_ranks(), n);
+
+        let elapsed = start.elapsed();
+        assert!(elapsed < Duration::from_secs(2), "took {elapsed:?}");
+    }
+
+    #[test]
+    fn factors() {
+        let factors = Factors::of(360);
+        assert_eq!(factors.primes[..factors.len], [2, 3, 5]);
+        assert_eq!(factors.exponents[..factors.len], [3, 2, 1]);
+        assert_eq!(factors.product(&factors.exponents), 360);
+
+        assert_eq!(Factors::of(1).len, 0);
+        let factors = Factors::of(usize::MAX);
+        assert_eq!(factors.product(&factors.exponents), usize::MAX);
+    }
+
+    #[test]
+    fn neighbors() {
+        let blocks = BlockDecomposition::new([30, 30, 30], 27);
+        assert_eq!(blocks.grid(), [3, 3, 3]);
+
+        let center = blocks.rank_at([1, 1, 1]);
+        let around: Vec<_> = blocks.neighbors(center).collect();
+        assert_eq!(around, [4, 22, 10, 16, 12, 14]);
+
+        let corner = blocks.rank_at([0, 0, 0]);
+        let around: Vec<_> = blocks.neighbors(corner).collect();
+        assert_eq!(around, [9, 3, 1]);
+
+        assert_eq!(blocks.neighbor(center, 2, 2), None);
+        assert_eq!(blocks.neighbor(corner, 2, 2), Some(2));
+    }
+}
@@ -0,0 +1,257 @@
+//! Balanced parts of a sorted slice that never split a run of equal
+//! keys, for grouping or deduplicating in parallel.
+
+use crate::layout::PartsLayout;
+
+pub trait PartsByKeyExt<T> {
+    /// Returns an iterator that divides the sorted slice into
+    /// `n_parts` parts, moving each balanced boundary to the nearest
+    /// point where `key_fn` changes.
+    ///
+    /// The slice must be sorted (or at least grouped) by `key_fn`. A
+    /// run of equal keys then always ends up within a single part.
+    ///
+    /// Moving the boundaries can leave parts empty, when a run of equal
+    /// keys covers a whole part. These are still yielded, as empty
+    /// slices, so that part `i` always corresponds to balanced part `i`.
+    /// [`KeyParts::empty_parts`] lists them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n_parts` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sheps_chunkerino::by_key::PartsByKeyExt;
+    ///
+    /// let slice = [1, 1, 2, 2, 2, 2, 3, 4];
+    /// let parts: Vec<_> = slice.parts_by_key(3, |&x| x).collect();
+    /// assert_eq!(parts, [&[1, 1][..], &[2, 2, 2, 2], &[3, 4]]);
+    ///
+    /// let parts = slice.parts_by_key(4, |&x| x);
+    /// assert_eq!(parts.empty_parts().collect::<Vec<_>>(), [1]);
+    /// ```
+    fn parts_by_key<K, F>(&self, n_parts: usize, key_fn: F) -> KeyParts<'_, T, F>
+    where
+        K: PartialEq,
+        F: Fn(&T) -> K;
+}
+
+impl<T> PartsByKeyExt<T> for [T] {
+    fn parts_by_key<K, F>(&self, n_parts: usize, key_fn: F) -> KeyParts<'_, T, F>
+    where
+        K: PartialEq,
+        F: Fn(&T) -> K,
+    {
+        let layout = PartsLayout::new(self.len(), n_parts);
+
+        KeyParts {
+            slice: self,
+            layout,
+            key_fn,
+            front: 0,
+            back: layout.n_parts(),
+        }
+    }
+}
+
+/// Iterator returned by [`PartsByKeyExt::parts_by_key`].
+pub struct KeyParts<'a, T, F> {
+    slice: &'a [T],
+    layout: PartsLayout,
+    key_fn: F,
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T, K, F> KeyParts<'a, T, F>
+where
+    K: PartialEq,
+    F: Fn(&T) -> K,
+{
+    /// Where part `k` starts, after moving it to the nearest key change.
+    /// `k` may be the number of parts, which gives the slice length.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is greater than the number of parts.
+    pub fn boundary(&self, k: usize) -> usize {
+        let balanced = if k == self.layout.n_parts() {
+            self.layout.len()
+        } else {
+            self.layout.part_range(k).start
+        };
+
+        self.snap(balanced)
+    }
+
+    /// The parts that ended up empty, because a run of equal keys
+    /// covered them entirely. Their elements were merged into the
+    /// neighbouring parts.
+    pub fn empty_parts(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.layout.n_parts()).filter(move |&k| self.boundary(k) == self.boundary(k + 1))
+    }
+
+    /// Moves `b` to the nearest edge of the run of equal keys it is in,
+    /// preferring the start of the run on a tie.
+    fn snap(&self, b: usize) -> usize {
+        let slice = self.slice;
+        if b == 0 || b == slice.len() {
+            return b;
+        }
+
+        let key = (self.key_fn)(&slice[b]);
+        if (self.key_fn)(&slice[b - 1]) != key {
+            return b;
+        }
+
+        let is_run = |x: &T| (self.key_fn)(x) == key;
+
+        // Gallop away from `b` in both directions in lockstep, so the
+        // search costs O(log d), where d is the distance to the nearer
+        // edge.
+        let mut step = 1;
+        loop {
+            let lo = b.saturating_sub(step);
+            let hi = b.saturating_add(step).min(slice.len());
+
+            let start_found = lo == 0 || !is_run(&slice[lo - 1]);
+            let end_found = hi == slice.len() || !is_run(&slice[hi]);
+
+            // Once one edge is within `step`, it is nearer than any edge
+            // that is not. Equal keys form a suffix of `lo..b` and a
+            // prefix of `b..hi`.
+            let start = || lo + slice[lo..b].partition_point(|x| !is_run(x));
+            let end = || b + slice[b..hi].partition_point(is_run);
+
+            match (start_found, end_found) {
+                (true, true) => {
+                    let (start, end) = (start(), end());
+                    return if b - start <= end - b { start } else { end };
+                }
+                (true, false) => return start(),
+                (false, true) => return end(),
+                (false, false) => step *= 2,
+            }
+        }
+    }
+}
+
+impl<'a, T, K, F> Iterator for KeyParts<'a, T, F>
+where
+    K: PartialEq,
+    F: Fn(&T) -> K,
+{
+    type Item = &'a [T];
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        let part = &self.slice[self.boundary(self.front)..self.boundary(self.front + 1)];
+        self.front += 1;
+
+        Some(part)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T, K, F> DoubleEndedIterator for KeyParts<'_, T, F>
+where
+    K: PartialEq,
+    F: Fn(&T) -> K,
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        self.back -= 1;
+
+        Some(&self.slice[self.boundary(self.back)..self.boundary(self.back + 1)])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conformance;
+    use std::vec::Vec;
+
+    /// Nearest key change to `b`, found by walking.
+    fn snap_slowly(keys: &[usize], b: usize) -> usize {
+        if b == 0 || b == keys.len() || keys[b - 1] != keys[b] {
+            return b;
+        }
+        let start = (0..b)
+            .rev()
+            .find(|&i| keys[i] != keys[b])
+            .map_or(0, |i| i + 1);
+        let end = (b..keys.len())
+            .find(|&i| keys[i] != keys[b])
+            .unwrap_or(keys.len());
+        if b - start <= end - b {
+            start
+        } else {
+            end
+        }
+    }
+
+    #[test]
+    fn runs_never_straddle_parts() {
+        for (items, n) in conformance::cases() {
+            let ctx = format!("len={}, n={n}", items.len());
+            // Runs of lengths 1, 2, 3, ...
+            let keys: Vec<_> = items.iter().map(|&i| (i * 8).isqrt()).collect();
+            let layout = PartsLayout::new(keys.len(), n);
+            let parts: Vec<_> = keys.parts_by_key(n, |&k| k).collect();
+
+            assert_eq!(parts.len(), layout.n_parts(), "{ctx}");
+            assert_eq!(parts.concat(), keys, "{ctx}");
+
+            for w in parts.windows(2) {
+                if let (Some(last), Some(first)) = (w[0].last(), w[1].first()) {
+                    assert_ne!(last, first, "{ctx}");
+                }
+            }
+
+            let mut start = 0;
+            for (i, part) in parts.iter().enumerate() {
+                assert_eq!(
+                    start,
+                    snap_slowly(&keys, layout.part_range(i).start),
+                    "{ctx}"
+                );
+                start += part.len();
+            }
+
+            let empty: Vec<_> = (0..parts.len()).filter(|&i| parts[i].is_empty()).collect();
+            let parts = keys.parts_by_key(n, |&k| k);
+            assert_eq!(parts.empty_parts().collect::<Vec<_>>(), empty, "{ctx}");
+
+            let mut rev: Vec<_> = keys.parts_by_key(n, |&k| k).rev().collect();
+            rev.reverse();
+            assert_eq!(rev.concat(), keys, "{ctx}");
+        }
+    }
+
+    #[test]
+    fn one_long_run() {
+        // Balanced boundaries at 3, 6 and 8 snap to 0, 10 and 10.
+        let slice = [7; 10];
+        let parts: Vec<_> = slice.parts_by_key(4, |&x| x).collect();
+        assert_eq!(parts, [&[][..], &slice, &[], &[]]);
+
+        let parts = slice.parts_by_key(4, |&x| x);
+        assert_eq!(parts.empty_parts().collect::<Vec<_>>(), [0, 2, 3]);
+    }
+}
@@ -0,0 +1,272 @@
+//! Conformance suite shared by every `Parts` implementation.
+//!
+//! Invoke [`conformance_tests!`] inside a module's `mod test`, passing
+//! `rev` if its `Parts` is a `DoubleEndedIterator`, and `rev, split` if
+//! it also has `split_at_part` and `split_half`. Every `(len, n)`
+//! pair with `len <= max_len()` and `1 <= n <= 2 * len + 1` is checked.
+
+use std::{env, vec::Vec};
+
+/// Largest slice length the suite checks. Override with the
+/// `CONFORMANCE_MAX_LEN` environment variable.
+pub(crate) fn max_len() -> usize {
+    env::var("CONFORMANCE_MAX_LEN")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(100)
+}
+
+/// Every `(len, n)` pair the suite checks, along with a slice of
+/// length `len` whose elements are their own indices.
+pub(crate) fn cases() -> impl Iterator<Item = (Vec<usize>, usize)> {
+    (0..=max_len()).flat_map(|len| (1..=2 * len + 1).map(move |n| ((0..len).collect(), n)))
+}
+
+/// Checks coverage, contiguity, non-overlap, the length spread and the
+/// part count of `parts`, which were produced from `items` with `n`.
+pub(crate) fn check_parts(items: &[usize], n: usize, parts: &[&[usize]]) {
+    let ctx = || format!("len={}, n={n}", items.len());
+
+    assert_eq!(
+        parts.len(),
+        n.min(items.len()),
+        "{}: wrong number of parts",
+        ctx()
+    );
+
+    let mut offset = 0;
+    for (i, part) in parts.iter().enumerate() {
+        assert!(!part.is_empty(), "{}: part {i} is empty", ctx());
+        // Pointer equality rules out copies as well as gaps and overlaps.
+        assert_eq!(
+            part.as_ptr(),
+            items[offset..].as_ptr(),
+            "{}: part {i} does not start where part {} ended",
+            ctx(),
+            i.wrapping_sub(1)
+        );
+        offset += part.len();
+    }
+    assert_eq!(
+        offset,
+        items.len(),
+        "{}: parts do not cover the slice",
+        ctx()
+    );
+
+    if let (Some(min), Some(max)) = (
+        parts.iter().map(|p| p.len()).min(),
+        parts.iter().map(|p| p.len()).max(),
+    ) {
+        assert!(
+            max - min <= 1,
+            "{}: part lengths range from {min} to {max}",
+            ctx()
+        );
+    }
+}
+
+/// `(len, n)` pairs for ZST slices, all of them at the limits of
+/// `usize`.
+pub(crate) fn zst_cases() -> impl Iterator<Item = (usize, usize)> {
+    const M: usize = usize::MAX;
+
+    [M, M - 1, M / 2, M / 2 + 1].into_iter().flat_map(|len| {
+        [1, 2, 3, 7, 64, len - 1, len, len.saturating_add(1), M]
+            .into_iter()
+            .map(move |n| (len, n.max(1)))
+    })
+}
+
+/// Checks the parts of a ZST slice of length `len` split into `n`.
+///
+/// Only the first [`ZST_PARTS`] parts are looked at, so this finishes
+/// no matter how many parts there are. If that is all of them, their
+/// lengths must add up to `len` without overflowing.
+pub(crate) fn check_zst_parts<'a>(len: usize, n: usize, parts: impl Iterator<Item = &'a [()]>) {
+    let count = n.min(len);
+    let small = len / n;
+
+    let mut total = 0usize;
+    let mut seen = 0;
+    for part in parts.take(ZST_PARTS) {
+        assert!(
+            part.len() == small || part.len() == small + 1,
+            "len={len}, n={n}: part {seen} has length {}",
+            part.len()
+        );
+        assert!(!part.is_empty(), "len={len}, n={n}: part {seen} is empty");
+        total = total
+            .checked_add(part.len())
+            .unwrap_or_else(|| panic!("len={len}, n={n}: lengths overflow"));
+        seen += 1;
+    }
+
+    assert_eq!(
+        seen,
+        count.min(ZST_PARTS),
+        "len={len}, n={n}: wrong number of parts"
+    );
+    if count <= ZST_PARTS {
+        assert_eq!(total, len, "len={len}, n={n}: parts do not cover the slice");
+    }
+}
+
+/// How many parts [`check_zst_parts`] looks at.
+pub(crate) const ZST_PARTS: usize = 64;
+
+/// Checks that `size_hint` brackets the number of remaining parts at
+/// every step of `iter`.
+pub(crate) fn check_size_hint<I: Iterator>(mut iter: I, expected: usize, ctx: &str) {
+    for remaining in (0..=expected).rev() {
+        let (lower, upper) = iter.size_hint();
+        assert!(
+            lower <= remaining,
+            "{ctx}: lower bound {lower} > {remaining}"
+        );
+        if let Some(upper) = upper {
+            assert!(
+                upper >= remaining,
+                "{ctx}: upper bound {upper} < {remaining}"
+            );
+        }
+        assert_eq!(iter.next().is_some(), remaining > 0, "{ctx}");
+    }
+}
+
+/// Checks that iterating from the back, and alternating between both
+/// ends, yields the same parts as iterating forwards.
+pub(crate) fn check_rev<'a, I>(make: impl Fn() -> I, forward: &[&'a [usize]], ctx: &str)
+where
+    I: DoubleEndedIterator<Item = &'a [usize]>,
+{
+    let mut backward: Vec<_> = make().rev().collect();
+    backward.reverse();
+    assert_eq!(backward, forward, "{ctx}: reverse iteration differs");
+
+    let mut iter = make();
+    let (mut front, mut back) = (Vec::new(), Vec::new());
+    for i in 0.. {
+        let next = if i % 2 == 0 {
+            iter.next().map(|p| front.push(p))
+        } else {
+            iter.next_back().map(|p| back.push(p))
+        };
+        if next.is_none() {
+            break;
+        }
+    }
+    front.extend(back.into_iter().rev());
+    assert_eq!(front, forward, "{ctx}: alternating iteration differs");
+}
+
+/// Checks that splitting at every part boundary gives two halves of the
+/// right lengths that chain back into `forward`, also after taking a
+/// part off each end.
+pub(crate) fn check_split<'a, I>(
+    make: impl Fn() -> I,
+    split_at: impl Fn(I, usize) -> (I, I),
+    forward: &[&'a [usize]],
+    ctx: &str,
+) where
+    I: DoubleEndedIterator<Item = &'a [usize]> + ExactSizeIterator,
+{
+    for trim in [false, true] {
+        let expected = match forward {
+            [_, inner @ .., _] if trim => inner,
+            _ if trim => continue,
+            _ => forward,
+        };
+
+        for k in 0..=expected.len() {
+            let ctx = format!("{ctx}, trim={trim}, k={k}");
+            let mut iter = make();
+            if trim {
+                iter.next();
+                iter.next_back();
+            }
+
+            let (left, right) = split_at(iter, k);
+            assert_eq!(left.len(), k, "{ctx}: left length");
+            assert_eq!(right.len(), expected.len() - k, "{ctx}: right length");
+
+            let chained: Vec<_> = left.chain(right).collect();
+            assert_eq!(chained, expected, "{ctx}: halves differ");
+        }
+    }
+}
+
+macro_rules! conformance_tests {
+    () => {
+        #[test]
+        fn conformance() {
+            for (items, n) in $crate::conformance::cases() {
+                let ctx = format!("len={}, n={n}", items.len());
+                let parts: Vec<_> = Parts::new(&items, n).collect();
+
+                $crate::conformance::check_parts(&items, n, &parts);
+                $crate::conformance::check_size_hint(Parts::new(&items, n), parts.len(), &ctx);
+            }
+        }
+
+        #[test]
+        fn conformance_zst() {
+            let items = [(); usize::MAX];
+
+            for (len, n) in $crate::conformance::zst_cases() {
+                $crate::conformance::check_zst_parts(len, n, Parts::new(&items[..len], n));
+            }
+        }
+    };
+    (rev) => {
+        $crate::conformance::conformance_tests!();
+
+        #[test]
+        fn conformance_rev() {
+            for (items, n) in $crate::conformance::cases() {
+                let ctx = format!("len={}, n={n}", items.len());
+                let parts: Vec<_> = Parts::new(&items, n).collect();
+
+                $crate::conformance::check_rev(|| Parts::new(&items, n), &parts, &ctx);
+            }
+
+            let items = [(); usize::MAX];
+
+            for (len, n) in $crate::conformance::zst_cases() {
+                $crate::conformance::check_zst_parts(len, n, Parts::new(&items[..len], n).rev());
+            }
+        }
+    };
+    (rev, split) => {
+        $crate::conformance::conformance_tests!(rev);
+
+        #[test]
+        fn conformance_split() {
+            // Every split point of every case is a lot of parts, so only
+            // the shorter slices.
+            for (items, n) in $crate::conformance::cases().filter(|(items, _)| items.len() <= 40) {
+                let ctx = format!("len={}, n={n}", items.len());
+                let parts: Vec<_> = Parts::new(&items, n).collect();
+
+                $crate::conformance::check_split(
+                    || Parts::new(&items, n),
+                    Parts::split_at_part,
+                    &parts,
+                    &ctx,
+                );
+
+                let (left, right) = Parts::new(&items, n).split_half();
+                assert_eq!(left.len(), parts.len() / 2, "{ctx}");
+                assert!(left.chain(right).eq(parts.iter().copied()), "{ctx}");
+            }
+
+            let items = [(); usize::MAX];
+
+            for (len, n) in $crate::conformance::zst_cases() {
+                let (left, right) = Parts::new(&items[..len], n).split_half();
+                $crate::conformance::check_zst_parts(len, n, left.chain(right));
+            }
+        }
+    };
+}
+pub(crate) use conformance_tests;
@@ -0,0 +1,428 @@
+//! Partitioning the rows of a CSR (compressed sparse row) matrix by
+//! nonzeros rather than by rows, for sparse matrix-vector products and
+//! graph traversals where row densities differ wildly.
+//!
+//! Both splits only need the row pointer array: row `i` holds nonzeros
+//! `row_ptr[i]..row_ptr[i + 1]`.
+
+use core::ops::Range;
+
+use crate::layout::{check_boundaries, LayoutError, PartsLayout};
+
+/// Splits the rows described by `row_ptr` into a maximum of `n_parts`
+/// contiguous row ranges with roughly equal numbers of nonzeros.
+///
+/// Each boundary is the row start nearest to its share of the
+/// nonzeros, found by binary search, so a part is off by at most one
+/// row's worth of nonzeros. A row denser than a whole share can leave
+/// neighbouring parts empty; see [`csr_merge_parts`] for a split that
+/// stays balanced regardless. Without any nonzeros, the rows are
+/// balanced instead.
+///
+/// Fails if `row_ptr` does not start at 0 or decreases.
+///
+/// # Panics
+///
+/// Panics if `n_parts` is 0.
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::csr::csr_row_parts;
+///
+/// // Rows with 1, 1, 6, 1 and 1 nonzeros.
+/// let row_ptr = [0, 1, 2, 8, 9, 10];
+/// let parts: Vec<_> = csr_row_parts(&row_ptr, 3).unwrap().collect();
+/// assert_eq!(parts, [0..2, 2..3, 3..5]);
+/// ```
+pub fn csr_row_parts(row_ptr: &[usize], n_parts: usize) -> Result<CsrRowParts<'_>, LayoutError> {
+    check_boundaries(row_ptr)?;
+
+    let n_rows = row_ptr.len() - 1;
+    let rows = PartsLayout::new(n_rows, n_parts);
+    let n_parts = rows.n_parts();
+
+    Ok(CsrRowParts {
+        row_ptr,
+        rows,
+        front: 0,
+        back: n_parts,
+    })
+}
+
+/// Iterator returned by [`csr_row_parts`].
+pub struct CsrRowParts<'a> {
+    row_ptr: &'a [usize],
+    /// The balanced row layout, which sets the number of parts and is
+    /// used as is when there are no nonzeros.
+    rows: PartsLayout,
+    front: usize,
+    back: usize,
+}
+
+impl CsrRowParts<'_> {
+    /// The row where part `k` starts.
+    fn boundary(&self, k: usize) -> usize {
+        let n_rows = self.row_ptr.len() - 1;
+        let nnz = self.row_ptr[n_rows];
+        let n_parts = self.rows.n_parts();
+
+        if k == n_parts {
+            return n_rows;
+        } else if nnz == 0 {
+            return self.rows.part_range(k).start;
+        }
+
+        // Where part `k` would start in the balanced layout of the
+        // nonzeros, big parts first.
+        let (q, r) = (nnz / n_parts, nnz % n_parts);
+        let target = k * q + k.min(r);
+
+        let row = self.row_ptr.partition_point(|&p| p < target);
+        if row > 0 && target - self.row_ptr[row - 1] < self.row_ptr[row] - target {
+            row - 1
+        } else {
+            row
+        }
+    }
+}
+
+impl Iterator for CsrRowParts<'_> {
+    type Item = Range<usize>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        self.front += 1;
+        Some(self.boundary(self.front - 1)..self.boundary(self.front))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for CsrRowParts<'_> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        self.back -= 1;
+        Some(self.boundary(self.back)..self.boundary(self.back + 1))
+    }
+}
+
+impl ExactSizeIterator for CsrRowParts<'_> {}
+
+/// Splits the rows and nonzeros described by `row_ptr` into a maximum
+/// of `n_parts` parts, each with a balanced number of rows plus
+/// nonzeros.
+///
+/// This is the merge-based split of Merrill and Garland: merging the
+/// row ends with the nonzero indices gives `rows + nonzeros` steps,
+/// which are split with the balanced layout of `seri2_slice::Parts`.
+/// Parts may start and end partway through a row, so even a single row
+/// holding most of the nonzeros is spread over several parts.
+///
+/// Fails if `row_ptr` does not start at 0 or decreases, or if the
+/// number of rows plus nonzeros overflows.
+///
+/// # Panics
+///
+/// Panics if `n_parts` is 0.
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::csr::{csr_merge_parts, MergePart};
+///
+/// // Rows with 1, 8 and 1 nonzeros.
+/// let row_ptr = [0, 1, 9, 10];
+/// let parts: Vec<_> = csr_merge_parts(&row_ptr, 2).unwrap().collect();
+/// assert_eq!(
+///     parts,
+///     [
+///         MergePart { rows: 0..1, nonzeros: 0..6 },
+///         MergePart { rows: 1..3, nonzeros: 6..10 },
+///     ]
+/// );
+/// ```
+pub fn csr_merge_parts(
+    row_ptr: &[usize],
+    n_parts: usize,
+) -> Result<CsrMergeParts<'_>, LayoutError> {
+    check_boundaries(row_ptr)?;
+
+    let row_ends = &row_ptr[1..];
+    let nnz = row_ptr[row_ends.len()];
+    let len = row_ends
+        .len()
+        .checked_add(nnz)
+        .ok_or(LayoutError::Overflow)?;
+    let layout = PartsLayout::new(len, n_parts);
+
+    Ok(CsrMergeParts {
+        row_ends,
+        layout,
+        front: 0,
+        back: layout.n_parts(),
+    })
+}
+
+/// A part of a [`csr_merge_parts`] split.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MergePart {
+    /// The rows whose last nonzero falls in this part, or that are
+    /// empty and sit between its nonzeros.
+    ///
+    /// Their nonzeros may have started in earlier parts, whose partial
+    /// results have to be carried over.
+    pub rows: Range<usize>,
+    /// The nonzeros this part processes. Those past the start of
+    /// `rows.end` belong to a row that a later part finishes.
+    pub nonzeros: Range<usize>,
+}
+
+/// Iterator returned by [`csr_merge_parts`].
+pub struct CsrMergeParts<'a> {
+    /// `row_ptr` without its leading 0.
+    row_ends: &'a [usize],
+    layout: PartsLayout,
+    front: usize,
+    back: usize,
+}
+
+impl CsrMergeParts<'_> {
+    /// Finds where the first `diagonal` steps of the merge leave off:
+    /// `i` rows finished and `diagonal - i` nonzeros processed.
+    ///
+    /// A row end goes before the nonzero with the same index, since
+    /// that nonzero belongs to a later row.
+    fn split(&self, diagonal: usize) -> (usize, usize) {
+        let nnz = self.layout.len() - self.row_ends.len();
+        let mut lo = diagonal.saturating_sub(nnz);
+        let mut hi = diagonal.min(self.row_ends.len());
+
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.row_ends[mid] < diagonal - mid {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+
+        (lo, diagonal - lo)
+    }
+
+    fn part(&self, k: usize) -> MergePart {
+        let range = self.layout.part_range(k);
+        let (row_start, nz_start) = self.split(range.start);
+        let (row_end, nz_end) = self.split(range.end);
+
+        MergePart {
+            rows: row_start..row_end,
+            nonzeros: nz_start..nz_end,
+        }
+    }
+}
+
+impl Iterator for CsrMergeParts<'_> {
+    type Item = MergePart;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        self.front += 1;
+        Some(self.part(self.front - 1))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for CsrMergeParts<'_> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        self.back -= 1;
+        Some(self.part(self.back))
+    }
+}
+
+impl ExactSizeIterator for CsrMergeParts<'_> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{vec, vec::Vec};
+
+    /// A row pointer array with power-law row lengths and some empty
+    /// rows.
+    fn power_law(n_rows: usize, seed: u64) -> Vec<usize> {
+        let mut state = seed;
+        let mut row_ptr = vec![0];
+        for _ in 0..n_rows {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let len = match state >> 60 {
+                0 => 0,
+                15 => 200 + (state >> 50) as usize % 100,
+                x => 1 << (x / 3),
+            };
+            row_ptr.push(row_ptr.last().unwrap() + len);
+        }
+        row_ptr
+    }
+
+    /// `y = A x` for the matrix with all nonzeros `1`, where column `j`
+    /// of nonzero `j` is `j % x.len()`.
+    fn spmv_rows(row_ptr: &[usize], x: &[u64]) -> Vec<u64> {
+        row_ptr
+            .windows(2)
+            .map(|w| (w[0]..w[1]).map(|j| x[j % x.len()]).sum())
+            .collect()
+    }
+
+    #[test]
+    fn row_parts_balance_nonzeros() {
+        for seed in 0..20 {
+            for n_rows in [0, 1, 2, 7, 100, 1000] {
+                let row_ptr = power_law(n_rows, seed);
+                let nnz = row_ptr[n_rows];
+                let widest = row_ptr.windows(2).map(|w| w[1] - w[0]).max().unwrap_or(0);
+
+                for n in [1, 2, 3, 8, 64, n_rows + 1] {
+                    let ctx = format!("seed={seed}, rows={n_rows}, n={n}");
+                    let parts: Vec<_> = csr_row_parts(&row_ptr, n).unwrap().collect();
+                    assert_eq!(parts.len(), n.min(n_rows), "{ctx}");
+
+                    let mut row = 0;
+                    for part in &parts {
+                        assert_eq!(part.start, row, "{ctx}");
+                        row = part.end;
+
+                        let share = nnz.div_ceil(parts.len());
+                        let part_nnz = row_ptr[part.end] - row_ptr[part.start];
+                        assert!(part_nnz <= share + widest, "{ctx}: {part_nnz} nonzeros");
+                    }
+                    assert_eq!(row, n_rows, "{ctx}");
+
+                    let mut rev: Vec<_> = csr_row_parts(&row_ptr, n).unwrap().rev().collect();
+                    rev.reverse();
+                    assert_eq!(rev, parts, "{ctx}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn merge_parts_compute_spmv() {
+        let x: Vec<u64> = (1..=17).collect();
+
+        for seed in 0..20 {
+            for n_rows in [0, 1, 2, 7, 100, 1000] {
+                let row_ptr = power_law(n_rows, seed);
+                let nnz = row_ptr[n_rows];
+                let expected = spmv_rows(&row_ptr, &x);
+
+                for n in [1, 2, 3, 8, 64, n_rows + nnz + 1] {
+                    let ctx = format!("seed={seed}, rows={n_rows}, n={n}");
+                    let layout = PartsLayout::new(n_rows + nnz, n);
+                    let parts: Vec<_> = csr_merge_parts(&row_ptr, n).unwrap().collect();
+                    assert_eq!(parts.len(), layout.n_parts(), "{ctx}");
+
+                    // Each part finishes its rows and carries its
+                    // partial sum over to the next.
+                    let mut y = vec![0; n_rows];
+                    let mut carry = 0;
+                    let (mut row, mut nz) = (0, 0);
+                    for (k, part) in parts.iter().enumerate() {
+                        assert_eq!((part.rows.start, part.nonzeros.start), (row, nz), "{ctx}");
+                        assert_eq!(
+                            part.rows.len() + part.nonzeros.len(),
+                            layout.part_len(k),
+                            "{ctx}"
+                        );
+
+                        let mut j = part.nonzeros.start;
+                        for r in part.rows.clone() {
+                            while j < row_ptr[r + 1] {
+                                carry += x[j % x.len()];
+                                j += 1;
+                            }
+                            y[r] = carry;
+                            carry = 0;
+                        }
+                        while j < part.nonzeros.end {
+                            carry += x[j % x.len()];
+                            j += 1;
+                        }
+
+                        (row, nz) = (part.rows.end, part.nonzeros.end);
+                    }
+                    assert_eq!((row, nz), (n_rows, nnz), "{ctx}");
+                    assert_eq!(y, expected, "{ctx}");
+
+                    let mut rev: Vec<_> = csr_merge_parts(&row_ptr, n).unwrap().rev().collect();
+                    rev.reverse();
+                    assert_eq!(rev, parts, "{ctx}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn one_dense_row() {
+        let row_ptr = [0, 1, 1001, 1002];
+
+        let parts: Vec<_> = csr_row_parts(&row_ptr, 3).unwrap().collect();
+        assert_eq!(parts, [0..1, 1..2, 2..3]);
+
+        let parts: Vec<_> = csr_merge_parts(&row_ptr, 4).unwrap().collect();
+        for part in &parts {
+            assert!(part.nonzeros.len() >= 249, "{part:?}");
+        }
+    }
+
+    #[test]
+    fn empty_matrix() {
+        let parts: Vec<_> = csr_row_parts(&[0, 0, 0, 0], 2).unwrap().collect();
+        assert_eq!(parts, [0..2, 2..3]);
+        assert_eq!(csr_row_parts(&[0], 2).unwrap().count(), 0);
+        assert_eq!(csr_merge_parts(&[0], 2).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn rejects_bad_row_ptr() {
+        assert_eq!(
+            csr_row_parts(&[], 2).err(),
+            Some(LayoutError::NotStartingAtZero)
+        );
+        assert_eq!(
+            csr_merge_parts(&[0, 3, 2], 2).err(),
+            Some(LayoutError::Decreasing { index: 2 })
+        );
+        assert_eq!(
+            csr_merge_parts(&[0, usize::MAX], 2).err(),
+            Some(LayoutError::Overflow)
+        );
+    }
+}
@@ -0,0 +1,558 @@
+//! Partitioning the cells of a 2D or 3D grid into contiguous runs along
+//! a space-filling curve, which keeps each run compact without tying it
+//! to a rectangular block.
+//!
+//! Grids of any size are supported: the curve runs over the smallest
+//! power-of-two cube containing the grid, and cells outside the grid
+//! are skipped.
+
+use core::array;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use crate::layout::BoundaryLayout;
+use crate::layout::PartsLayout;
+
+/// The order in which a [`CurveGrid`] visits its cells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Curve {
+    /// Z-order, which interleaves the bits of the coordinates. Cheap,
+    /// but consecutive cells can be far apart.
+    Morton,
+    /// Consecutive cells are always neighbours in a power-of-two cube,
+    /// which gives runs with less surface than Morton order.
+    Hilbert,
+}
+
+/// A `D`-dimensional grid of cells, ordered along a [`Curve`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CurveGrid<const D: usize> {
+    dims: [usize; D],
+    curve: Curve,
+    /// The curve covers `2^bits` cells along each axis.
+    bits: u32,
+    n_cells: usize,
+}
+
+impl<const D: usize> CurveGrid<D> {
+    /// A grid of `dims[0]` x `dims[1]` x ... cells.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `D` is 0, if the number of cells overflows `usize`, or
+    /// if the enclosing power-of-two cube has more than `2^64` cells.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sheps_chunkerino::curve::{Curve, CurveGrid};
+    ///
+    /// let grid = CurveGrid::new([4, 4], Curve::Hilbert);
+    /// let runs: Vec<Vec<_>> = grid.runs(4).map(|run| run.collect()).collect();
+    /// assert_eq!(runs[0], [[0, 0], [1, 0], [1, 1], [0, 1]]);
+    /// assert_eq!(runs[1], [[0, 2], [0, 3], [1, 3], [1, 2]]);
+    /// ```
+    pub fn new(dims: [usize; D], curve: Curve) -> Self {
+        assert!(D > 0, "no dimensions to order");
+
+        let n_cells = dims
+            .iter()
+            .try_fold(1usize, |n, &d| n.checked_mul(d))
+            .expect("number of cells overflows usize");
+        let bits = dims
+            .iter()
+            .map(|&d| usize::BITS - (d.max(1) - 1).leading_zeros())
+            .max()
+            .unwrap_or(0);
+        assert!(
+            D as u64 * u64::from(bits) <= 64,
+            "grid too large for a 64-bit curve index"
+        );
+
+        Self {
+            dims,
+            curve,
+            bits,
+            n_cells,
+        }
+    }
+
+    #[inline]
+    pub fn dims(&self) -> [usize; D] {
+        self.dims
+    }
+
+    #[inline]
+    pub fn curve(&self) -> Curve {
+        self.curve
+    }
+
+    #[inline]
+    pub fn n_cells(&self) -> usize {
+        self.n_cells
+    }
+
+    /// All cells, in curve order.
+    #[inline]
+    pub fn cells(&self) -> Cells<D> {
+        self.cells_from(0, self.n_cells)
+    }
+
+    /// Splits the cells into a maximum of `n_runs` contiguous runs
+    /// along the curve, with cell counts following the balanced layout
+    /// of `seri2_slice::Parts`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n_runs` is 0.
+    #[inline]
+    pub fn runs(&self, n_runs: usize) -> Runs<D> {
+        let layout = PartsLayout::new(self.n_cells, n_runs);
+
+        Runs {
+            grid: *self,
+            layout,
+            front: 0,
+            back: layout.n_parts(),
+        }
+    }
+
+    /// Splits the cells into `n_runs` contiguous runs along the curve,
+    /// balancing the total `weight` of each run instead of its cell
+    /// count.
+    ///
+    /// Run `k` starts at the first cell where the weight of all cells
+    /// before it reaches `k / n_runs` of the total, so no run weighs
+    /// more than its share plus the heaviest cell. Runs may be empty.
+    /// If all weights are 0, cell counts are balanced instead.
+    ///
+    /// `weight` is called twice for every cell, in curve order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n_runs` is 0, or if the total weight overflows `u64`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sheps_chunkerino::curve::{Curve, CurveGrid};
+    ///
+    /// // All the work is in the left column.
+    /// let grid = CurveGrid::new([4, 4], Curve::Morton);
+    /// let runs = grid.weighted_runs(2, |[x, _]| if x == 0 { 10 } else { 1 });
+    /// let lens: Vec<_> = runs.map(|run| run.len()).collect();
+    /// assert_eq!(lens, [5, 11]);
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn weighted_runs<F>(&self, n_runs: usize, mut weight: F) -> WeightedRuns<D>
+    where
+        F: FnMut([usize; D]) -> u64,
+    {
+        assert!(n_runs > 0, "no runs to split into");
+
+        let total = self
+            .cells()
+            .try_fold(0u64, |total, cell| total.checked_add(weight(cell)))
+            .expect("total weight overflows u64");
+
+        let layout = if total == 0 {
+            let balanced = PartsLayout::new(self.n_cells, n_runs);
+            let mut boundaries: Vec<_> = balanced.boundaries().collect();
+            boundaries.resize(n_runs + 1, self.n_cells);
+            BoundaryLayout::new(boundaries)
+        } else {
+            let (total, n) = (u128::from(total), n_runs as u128);
+            let mut boundaries = Vec::with_capacity(n_runs + 1);
+            boundaries.push(0);
+
+            let mut before = 0u128;
+            for (i, cell) in self.cells().enumerate() {
+                while boundaries.len() < n_runs && before * n >= boundaries.len() as u128 * total {
+                    boundaries.push(i);
+                }
+                before += u128::from(weight(cell));
+            }
+            boundaries.resize(n_runs + 1, self.n_cells);
+            BoundaryLayout::new(boundaries)
+        }
+        .expect("weighted boundaries never decrease");
+
+        WeightedRuns {
+            grid: *self,
+            front: 0,
+            back: layout.n_parts(),
+            layout,
+        }
+    }
+
+    /// The cells with curve ranks `start..start + len`, counting only
+    /// cells inside the grid.
+    fn cells_from(&self, start: usize, len: usize) -> Cells<D> {
+        Cells {
+            grid: *self,
+            index: if len == 0 { 0 } else { self.seek(start) },
+            remaining: len,
+        }
+    }
+
+    /// The curve index of the cell with curve rank `rank`.
+    fn seek(&self, mut rank: usize) -> u64 {
+        let mut index = 0;
+
+        // Descend through the aligned sub-cubes, skipping those that
+        // hold no more than `rank` cells of the grid.
+        for level in (0..self.bits).rev() {
+            let block = 1u64 << (D as u32 * level);
+            loop {
+                let origin = self.block_origin(index, level);
+                let cells = self.cells_in(origin, 1 << level);
+                if rank < cells {
+                    break;
+                }
+                rank -= cells;
+                index += block;
+            }
+        }
+
+        index
+    }
+
+    /// The number of grid cells in the cube of side `side` at `origin`.
+    #[inline]
+    fn cells_in(&self, origin: [usize; D], side: usize) -> usize {
+        (0..D)
+            .map(|axis| self.dims[axis].saturating_sub(origin[axis]).min(side))
+            .product()
+    }
+
+    /// The corner of the cube of side `2^level` holding curve index
+    /// `index`. Aligned runs of `2^(D * level)` indices fill such cubes,
+    /// for both curves.
+    #[inline]
+    fn block_origin(&self, index: u64, level: u32) -> [usize; D] {
+        self.decode(index).map(|c| c >> level << level)
+    }
+
+    #[inline]
+    fn contains(&self, coords: [usize; D]) -> bool {
+        coords.iter().zip(&self.dims).all(|(c, d)| c < d)
+    }
+
+    /// The coordinates of curve index `index`.
+    fn decode(&self, index: u64) -> [usize; D] {
+        // Bit `b` of axis `i` is bit `b * D + (D - 1 - i)` of the index,
+        // so the first axis varies slowest.
+        let mut x: [u64; D] = array::from_fn(|axis| {
+            (0..self.bits).fold(0, |x, b| {
+                x | (index >> (b as usize * D + (D - 1 - axis)) & 1) << b
+            })
+        });
+
+        if self.curve == Curve::Hilbert && self.bits > 0 {
+            // Skilling, "Programming the Hilbert curve", AIP Conference
+            // Proceedings 707 (2004): TransposetoAxes.
+            let t = x[D - 1] >> 1;
+            for i in (1..D).rev() {
+                x[i] ^= x[i - 1];
+            }
+            x[0] ^= t;
+
+            let mut q: u64 = 2;
+            while q.trailing_zeros() < self.bits {
+                let p = q - 1;
+                for i in (0..D).rev() {
+                    if x[i] & q != 0 {
+                        x[0] ^= p;
+                    } else {
+                        let t = (x[0] ^ x[i]) & p;
+                        x[0] ^= t;
+                        x[i] ^= t;
+                    }
+                }
+                q <<= 1;
+            }
+        }
+
+        x.map(|c| c as usize)
+    }
+}
+
+/// The cells of a run, in curve order.
+#[derive(Clone, Debug)]
+pub struct Cells<const D: usize> {
+    grid: CurveGrid<D>,
+    /// The curve index to look at next. It may lie outside the grid.
+    index: u64,
+    remaining: usize,
+}
+
+impl<const D: usize> Iterator for Cells<D> {
+    type Item = [usize; D];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        loop {
+            let coords = self.grid.decode(self.index);
+            if self.grid.contains(coords) {
+                self.index += 1;
+                self.remaining -= 1;
+                return Some(coords);
+            }
+
+            // Skip the largest aligned cube around `coords` that misses
+            // the grid entirely.
+            let mut level = 1;
+            while level <= self.grid.bits
+                && self.index.trailing_zeros() >= D as u32 * level
+                && !self
+                    .grid
+                    .contains(self.grid.block_origin(self.index, level))
+            {
+                level += 1;
+            }
+            self.index += 1 << (D as u32 * (level - 1));
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<const D: usize> ExactSizeIterator for Cells<D> {}
+
+/// Iterator returned by [`CurveGrid::runs`].
+#[derive(Clone, Debug)]
+pub struct Runs<const D: usize> {
+    grid: CurveGrid<D>,
+    layout: PartsLayout,
+    front: usize,
+    back: usize,
+}
+
+impl<const D: usize> Iterator for Runs<D> {
+    type Item = Cells<D>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        let range = self.layout.part_range(self.front);
+        self.front += 1;
+        Some(self.grid.cells_from(range.start, range.len()))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<const D: usize> DoubleEndedIterator for Runs<D> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        self.back -= 1;
+        let range = self.layout.part_range(self.back);
+        Some(self.grid.cells_from(range.start, range.len()))
+    }
+}
+
+impl<const D: usize> ExactSizeIterator for Runs<D> {}
+
+/// Iterator returned by [`CurveGrid::weighted_runs`].
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct WeightedRuns<const D: usize> {
+    grid: CurveGrid<D>,
+    layout: BoundaryLayout,
+    front: usize,
+    back: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl<const D: usize> WeightedRuns<D> {
+    /// The curve ranks where each run starts, followed by the number of
+    /// cells.
+    #[inline]
+    pub fn layout(&self) -> &BoundaryLayout {
+        &self.layout
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<const D: usize> Iterator for WeightedRuns<D> {
+    type Item = Cells<D>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        let range = self.layout.part_range(self.front);
+        self.front += 1;
+        Some(self.grid.cells_from(range.start, range.len()))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<const D: usize> DoubleEndedIterator for WeightedRuns<D> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        self.back -= 1;
+        let range = self.layout.part_range(self.back);
+        Some(self.grid.cells_from(range.start, range.len()))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<const D: usize> ExactSizeIterator for WeightedRuns<D> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{collections::HashSet, vec::Vec};
+
+    /// Checks that `grid` visits every cell once, and that its runs and
+    /// weighted runs split that order as promised.
+    fn check_grid<const D: usize>(grid: CurveGrid<D>) {
+        let ctx = format!("{:?} {:?}", grid.curve(), grid.dims());
+        let cells: Vec<_> = grid.cells().collect();
+        assert_eq!(cells.len(), grid.n_cells(), "{ctx}");
+        let unique: HashSet<_> = cells.iter().collect();
+        assert_eq!(unique.len(), cells.len(), "{ctx}");
+        assert!(cells.iter().all(|&c| grid.contains(c)), "{ctx}");
+
+        for n in [1, 2, 3, 5, 8, grid.n_cells() + 1] {
+            let layout = PartsLayout::new(grid.n_cells(), n);
+            let runs: Vec<Vec<_>> = grid.runs(n).map(Iterator::collect).collect();
+            assert_eq!(runs.len(), layout.n_parts(), "{ctx}, n={n}");
+            for (i, run) in runs.iter().enumerate() {
+                assert_eq!(run.len(), layout.part_len(i), "{ctx}, n={n}");
+            }
+            assert_eq!(runs.concat(), cells, "{ctx}, n={n}");
+
+            let mut rev: Vec<Vec<_>> = grid.runs(n).rev().map(Iterator::collect).collect();
+            rev.reverse();
+            assert_eq!(rev, runs, "{ctx}, n={n}");
+
+            let weight = |c: [usize; D]| (c.iter().sum::<usize>() % 4) as u64;
+            let total: u64 = cells.iter().map(|&c| weight(c)).sum();
+            let runs: Vec<Vec<_>> = grid
+                .weighted_runs(n, weight)
+                .map(Iterator::collect)
+                .collect();
+            assert_eq!(runs.len(), n, "{ctx}, n={n}");
+            assert_eq!(runs.concat(), cells, "{ctx}, n={n}");
+            for run in &runs {
+                let run_weight: u64 = run.iter().map(|&c| weight(c)).sum();
+                assert!(
+                    run_weight * n as u64 <= total + 3 * n as u64,
+                    "{ctx}, n={n}: run weighs {run_weight} of {total}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_cell_once() {
+        for curve in [Curve::Morton, Curve::Hilbert] {
+            for w in 0..=9 {
+                for h in 0..=9 {
+                    check_grid(CurveGrid::new([w, h], curve));
+                }
+            }
+            for dims in [[1, 1, 1], [4, 4, 4], [5, 3, 2], [1, 17, 3], [8, 8, 1]] {
+                check_grid(CurveGrid::new(dims, curve));
+            }
+            check_grid(CurveGrid::new([1000, 1], curve));
+            check_grid(CurveGrid::new([13], curve));
+        }
+    }
+
+    #[test]
+    fn hilbert_steps_to_neighbours() {
+        fn check<const D: usize>(dims: [usize; D]) {
+            let cells: Vec<_> = CurveGrid::new(dims, Curve::Hilbert).cells().collect();
+            for w in cells.windows(2) {
+                let dist: usize = (0..D).map(|i| w[0][i].abs_diff(w[1][i])).sum();
+                assert_eq!(dist, 1, "{dims:?}: {:?} -> {:?}", w[0], w[1]);
+            }
+        }
+
+        check([2, 2]);
+        check([16, 16]);
+        check([8, 8, 8]);
+        check([4, 4, 4, 4]);
+        check([16]);
+    }
+
+    #[test]
+    fn morton_order() {
+        let cells: Vec<_> = CurveGrid::new([4, 4], Curve::Morton)
+            .cells()
+            .take(6)
+            .collect();
+        assert_eq!(cells, [[0, 0], [0, 1], [1, 0], [1, 1], [0, 2], [0, 3]]);
+
+        // Cells outside the 3x3 grid are skipped.
+        let cells: Vec<_> = CurveGrid::new([3, 3], Curve::Morton).cells().collect();
+        assert_eq!(
+            cells,
+            [
+                [0, 0],
+                [0, 1],
+                [1, 0],
+                [1, 1],
+                [0, 2],
+                [1, 2],
+                [2, 0],
+                [2, 1],
+                [2, 2]
+            ]
+        );
+    }
+
+    #[test]
+    fn weighted_runs_follow_the_work() {
+        let grid = CurveGrid::new([64, 64], Curve::Hilbert);
+        // A hot spot of 100x weight in one corner.
+        let weight = |[x, y]: [usize; 2]| if x < 8 && y < 8 { 100 } else { 1 };
+
+        let runs = grid.weighted_runs(4, weight);
+        let lens: Vec<_> = runs.clone().map(|run| run.len()).collect();
+        assert!(lens[0] < lens[3] / 4, "{lens:?}");
+        assert_eq!(runs.layout().len(), grid.n_cells());
+
+        let zero = grid.weighted_runs(4, |_| 0);
+        let lens: Vec<_> = zero.map(|run| run.len()).collect();
+        assert_eq!(lens, [1024; 4]);
+
+        let few = CurveGrid::new([2, 1], Curve::Morton).weighted_runs(3, |_| 0);
+        let lens: Vec<_> = few.map(|run| run.len()).collect();
+        assert_eq!(lens, [1, 1, 0]);
+    }
+}
@@ -1,4 +1,6 @@
-use std::{cmp, iter::Chain, slice::Chunks};
+use core::{cmp, iter::Chain, slice::Chunks};
+
+use crate::layout::PartsLayout;
 
 super::ext!();
 
@@ -9,13 +11,12 @@ pub struct Parts<'a, T> {
 
 impl<'a, T> Parts<'a, T> {
     pub fn new(slice: &'a [T], n_parts: usize) -> Self {
-        let len = slice.len();
+        let layout = PartsLayout::new(slice.len(), n_parts);
 
-        let tail_chunk_len = len / n_parts;
-        let head_chunk_len = tail_chunk_len + 1;
-        let n_head = len - (tail_chunk_len * n_parts);
+        let head_chunk_len = layout.big_len();
+        let tail_chunk_len = layout.small_len();
 
-        let midpoint = head_chunk_len * n_head;
+        let midpoint = head_chunk_len * layout.n_big();
 
         let (head, tail) = slice.split_at(midpoint);
 
@@ -75,3 +76,10 @@ impl<'a, T> Iterator for Parts<'a, T> {
 // impl<'a, T> ExactSizeIterator for Parts<'a, T> {}
 
 // impl<'a, T> FusedIterator for Parts<'a, T> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    crate::conformance::conformance_tests!();
+}
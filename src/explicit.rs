@@ -0,0 +1,286 @@
+//! Parts at split points chosen by the caller, for example from a file
+//! index or a previous run, instead of balanced ones.
+
+use core::iter::FusedIterator;
+
+use crate::{
+    layout::{check_boundaries, LayoutError},
+    split::split_at_unchecked,
+};
+
+/// Splits `slice` into consecutive parts of the given `sizes`.
+///
+/// Same as [`Parts::from_sizes`].
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::explicit::split_sizes;
+///
+/// let slice = ['l', 'o', 'r', 'e', 'm'];
+/// let mut iter = split_sizes(&slice, &[1, 0, 4]).unwrap();
+/// assert_eq!(iter.next().unwrap(), &['l']);
+/// assert_eq!(iter.next().unwrap(), &[]);
+/// assert_eq!(iter.next().unwrap(), &['o', 'r', 'e', 'm']);
+/// assert!(iter.next().is_none());
+/// ```
+#[inline]
+pub fn split_sizes<'a, 'b, T>(
+    slice: &'a [T],
+    sizes: &'b [usize],
+) -> Result<Parts<'a, 'b, T>, LayoutError> {
+    Parts::from_sizes(slice, sizes)
+}
+
+/// An iterator over parts of a slice at explicit split points.
+///
+/// Unlike the balanced `Parts`, parts may have any length, including
+/// zero. The split points are checked once, when the iterator is built.
+pub struct Parts<'a, 'b, T> {
+    slice: &'a [T],
+    cuts: Cuts<'b>,
+}
+
+#[derive(Clone, Copy)]
+enum Cuts<'b> {
+    /// Offsets of the remaining parts, starting with the start of the
+    /// first and ending with the end of the last.
+    Boundaries(&'b [usize]),
+    /// Lengths of the remaining parts.
+    Sizes(&'b [usize]),
+}
+
+impl<'a, 'b, T> Parts<'a, 'b, T> {
+    /// Splits `slice` so that part `i` is
+    /// `slice[boundaries[i]..boundaries[i + 1]]`.
+    ///
+    /// `boundaries` must start at 0, never decrease, and end at
+    /// `slice.len()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sheps_chunkerino::{explicit::Parts, layout::LayoutError};
+    ///
+    /// let slice = ['l', 'o', 'r', 'e', 'm'];
+    /// let parts: Vec<_> = Parts::from_boundaries(&slice, &[0, 2, 5]).unwrap().collect();
+    /// assert_eq!(parts, [&['l', 'o'][..], &['r', 'e', 'm']]);
+    ///
+    /// assert_eq!(
+    ///     Parts::from_boundaries(&slice, &[0, 2, 6]).err(),
+    ///     Some(LayoutError::LengthMismatch { expected: 5, actual: 6 })
+    /// );
+    /// ```
+    pub fn from_boundaries(slice: &'a [T], boundaries: &'b [usize]) -> Result<Self, LayoutError> {
+        check_boundaries(boundaries)?;
+
+        let end = boundaries[boundaries.len() - 1];
+        if end != slice.len() {
+            return Err(LayoutError::LengthMismatch {
+                expected: slice.len(),
+                actual: end,
+            });
+        }
+
+        // SAFETY: Just checked.
+        Ok(unsafe { Self::from_boundaries_unchecked(slice, boundaries) })
+    }
+
+    /// Splits `slice` into consecutive parts of the given `sizes`, which
+    /// must add up to `slice.len()`.
+    pub fn from_sizes(slice: &'a [T], sizes: &'b [usize]) -> Result<Self, LayoutError> {
+        let total = sizes
+            .iter()
+            .try_fold(0usize, |total, &size| total.checked_add(size))
+            .ok_or(LayoutError::Overflow)?;
+
+        if total != slice.len() {
+            return Err(LayoutError::LengthMismatch {
+                expected: slice.len(),
+                actual: total,
+            });
+        }
+
+        Ok(Self {
+            slice,
+            cuts: Cuts::Sizes(sizes),
+        })
+    }
+
+    /// # Safety
+    ///
+    /// `boundaries` must start at 0, never decrease, and end at
+    /// `slice.len()`.
+    #[inline]
+    pub(crate) unsafe fn from_boundaries_unchecked(
+        slice: &'a [T],
+        boundaries: &'b [usize],
+    ) -> Self {
+        Self {
+            slice,
+            cuts: Cuts::Boundaries(boundaries),
+        }
+    }
+}
+
+impl<'a, T> Iterator for Parts<'a, '_, T> {
+    type Item = &'a [T];
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let size = match self.cuts {
+            Cuts::Boundaries(b @ [start, end, ..]) => {
+                self.cuts = Cuts::Boundaries(&b[1..]);
+                end - start
+            }
+            Cuts::Sizes([size, rest @ ..]) => {
+                self.cuts = Cuts::Sizes(rest);
+                *size
+            }
+            _ => return None,
+        };
+
+        // SAFETY: The cuts were checked to add up to the slice length.
+        let (head, tail) = unsafe { split_at_unchecked(self.slice, size) };
+        self.slice = tail;
+
+        Some(head)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = match self.cuts {
+            Cuts::Boundaries(b) => b.len().saturating_sub(1),
+            Cuts::Sizes(s) => s.len(),
+        };
+        (n, Some(n))
+    }
+}
+
+impl<T> ExactSizeIterator for Parts<'_, '_, T> {}
+
+impl<T> FusedIterator for Parts<'_, '_, T> {}
+
+impl<T> DoubleEndedIterator for Parts<'_, '_, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let size = match self.cuts {
+            Cuts::Boundaries(b @ [.., start, end]) => {
+                self.cuts = Cuts::Boundaries(&b[..b.len() - 1]);
+                end - start
+            }
+            Cuts::Sizes([rest @ .., size]) => {
+                self.cuts = Cuts::Sizes(rest);
+                *size
+            }
+            _ => return None,
+        };
+
+        // SAFETY: The cuts were checked to add up to the slice length.
+        let (head, tail) = unsafe { split_at_unchecked(self.slice, self.slice.len() - size) };
+        self.slice = head;
+
+        Some(tail)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{conformance, layout::PartsLayout, seri2_slice};
+    use std::vec::Vec;
+
+    #[test]
+    fn matches_balanced_parts() {
+        for (items, n) in conformance::cases() {
+            let ctx = format!("len={}, n={n}", items.len());
+            let layout = PartsLayout::new(items.len(), n);
+            let expected: Vec<_> = seri2_slice::Parts::new(&items, n).collect();
+
+            let boundaries: Vec<_> = layout.boundaries().collect();
+            let parts: Vec<_> = Parts::from_boundaries(&items, &boundaries)
+                .unwrap()
+                .collect();
+            assert_eq!(parts, expected, "{ctx}");
+            conformance::check_parts(&items, n, &parts);
+            conformance::check_size_hint(
+                Parts::from_boundaries(&items, &boundaries).unwrap(),
+                parts.len(),
+                &ctx,
+            );
+            conformance::check_rev(
+                || Parts::from_boundaries(&items, &boundaries).unwrap(),
+                &parts,
+                &ctx,
+            );
+
+            let sizes: Vec<_> = expected.iter().map(|p| p.len()).collect();
+            let parts: Vec<_> = split_sizes(&items, &sizes).unwrap().collect();
+            assert_eq!(parts, expected, "{ctx}");
+            conformance::check_rev(|| split_sizes(&items, &sizes).unwrap(), &parts, &ctx);
+        }
+    }
+
+    #[test]
+    fn same_traits_as_balanced_parts() {
+        fn traits<I: DoubleEndedIterator + ExactSizeIterator + FusedIterator>(_: &I) {}
+
+        let a = [0, 1, 2];
+        traits(&seri2_slice::Parts::new(&a, 2));
+        traits(&Parts::from_boundaries(&a, &[0, 1, 3]).unwrap());
+        traits(&split_sizes(&a, &[1, 2]).unwrap());
+    }
+
+    #[test]
+    fn empty_parts() {
+        let a = [0, 1, 2, 3, 4];
+        let expected = [&[][..], &[0, 1], &[], &[2, 3, 4], &[]];
+
+        let parts: Vec<_> = Parts::from_boundaries(&a, &[0, 0, 2, 2, 5, 5])
+            .unwrap()
+            .collect();
+        assert_eq!(parts, expected);
+
+        let mut parts: Vec<_> = split_sizes(&a, &[0, 2, 0, 3, 0]).unwrap().rev().collect();
+        parts.reverse();
+        assert_eq!(parts, expected);
+
+        assert_eq!(Parts::from_boundaries(&[(); 0], &[0]).unwrap().count(), 0);
+        assert_eq!(split_sizes(&[(); 0], &[]).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn rejects_bad_cuts() {
+        use LayoutError::*;
+
+        let a = [0; 5];
+        let err = |boundaries: &[usize]| Parts::from_boundaries(&a, boundaries).err();
+        assert_eq!(err(&[]), Some(NotStartingAtZero));
+        assert_eq!(err(&[1, 5]), Some(NotStartingAtZero));
+        assert_eq!(err(&[0, 3, 2, 5]), Some(Decreasing { index: 2 }));
+        assert_eq!(
+            err(&[0, 3, 4]),
+            Some(LengthMismatch {
+                expected: 5,
+                actual: 4
+            })
+        );
+        assert_eq!(
+            err(&[0, 3, 6]),
+            Some(LengthMismatch {
+                expected: 5,
+                actual: 6
+            })
+        );
+
+        let err = |sizes: &[usize]| split_sizes(&a, sizes).err();
+        assert_eq!(err(&[usize::MAX, 1]), Some(Overflow));
+        assert_eq!(
+            err(&[2, 2]),
+            Some(LengthMismatch {
+                expected: 5,
+                actual: 4
+            })
+        );
+    }
+}
@@ -0,0 +1,684 @@
+use core::{fmt, ops::Range};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use crate::explicit;
+use crate::seri2_slice::Parts;
+
+/// The sizes of the parts a slice of `len` elements is split into.
+///
+/// This is the layout [`seri2_slice::Parts`] walks: `n_big` parts of
+/// `big_len` elements, followed by `n_small` parts of
+/// `small_len == big_len - 1`. If all parts have the same length, they
+/// all count as big. There are never any empty parts, so a slice
+/// shorter than the requested number of parts is split into `len`
+/// parts of one element.
+///
+/// Computing a layout once and [applying](Self::apply) it to many
+/// slices of the same length skips the divisions in [`Parts::new`].
+///
+/// [`seri2_slice::Parts`]: crate::seri2_slice::Parts
+/// [`Parts::new`]: crate::seri2_slice::Parts::new
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::layout::PartsLayout;
+///
+/// const LAYOUT: PartsLayout = PartsLayout::new(5, 2);
+///
+/// assert_eq!(LAYOUT.part_range(0), 0..3);
+/// assert_eq!(LAYOUT.part_range(1), 3..5);
+/// assert_eq!(LAYOUT.part_of(3), 1);
+///
+/// let a = ['l', 'o', 'r', 'e', 'm'];
+/// let b = ['i', 'p', 's', 'u', 'm'];
+/// assert_eq!(LAYOUT.apply(&a).last(), Some(&['e', 'm'][..]));
+/// assert_eq!(LAYOUT.apply(&b).last(), Some(&['u', 'm'][..]));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PartsLayout {
+    big_len: usize,
+    n_big: usize,
+    small_len: usize,
+    n_small: usize,
+}
+
+impl PartsLayout {
+    /// Computes the layout of `len` elements split into a maximum of
+    /// `n_parts` parts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n_parts` is 0.
+    #[inline]
+    pub const fn new(len: usize, n_parts: usize) -> Self {
+        assert!(n_parts > 0, "n_parts must be greater than 0");
+
+        let chunk_size = len / n_parts;
+        let times_extra = len % n_parts;
+
+        // Each layout has exactly one representation, so that layouts
+        // compare equal whenever they split the same way.
+        if times_extra == 0 {
+            Self {
+                big_len: chunk_size,
+                n_big: if len == 0 { 0 } else { n_parts },
+                small_len: 0,
+                n_small: 0,
+            }
+        } else {
+            // Only `+ 1` when there are big parts, so that a single part
+            // of `usize::MAX` elements (of a ZST) cannot overflow.
+            Self {
+                big_len: chunk_size + 1,
+                n_big: times_extra,
+                small_len: chunk_size,
+                // Fewer elements than parts: only the one-element parts.
+                n_small: if chunk_size == 0 {
+                    0
+                } else {
+                    n_parts - times_extra
+                },
+            }
+        }
+    }
+
+    /// Rebuilds a layout from the sizes and counts its accessors report,
+    /// for example after sending them to another process.
+    ///
+    /// Fails unless these are exactly the sizes [`new`] would have
+    /// produced for some `len` and number of parts.
+    ///
+    /// [`new`]: Self::new
+    pub const fn from_sizes(
+        big_len: usize,
+        n_big: usize,
+        small_len: usize,
+        n_small: usize,
+    ) -> Result<Self, LayoutError> {
+        let raw = Self {
+            big_len,
+            n_big,
+            small_len,
+            n_small,
+        };
+
+        let (len, n_parts) = match (
+            checked_len(big_len, n_big, small_len, n_small),
+            n_big.checked_add(n_small),
+        ) {
+            (Some(len), Some(n_parts)) => (len, n_parts),
+            _ => return Err(LayoutError::Overflow),
+        };
+
+        let expected = if n_parts == 0 {
+            Self {
+                big_len: 0,
+                n_big: 0,
+                small_len: 0,
+                n_small: 0,
+            }
+        } else {
+            Self::new(len, n_parts)
+        };
+
+        if raw.big_len == expected.big_len
+            && raw.n_big == expected.n_big
+            && raw.small_len == expected.small_len
+            && raw.n_small == expected.n_small
+        {
+            Ok(raw)
+        } else {
+            Err(LayoutError::Unbalanced)
+        }
+    }
+
+    /// The number of elements this layout covers.
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.n_big * self.big_len + self.n_small * self.small_len
+    }
+
+    /// Whether this layout covers no elements, and so has no parts.
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.n_parts() == 0
+    }
+
+    /// The number of parts, which is the smaller of `len` and the
+    /// requested number of parts.
+    #[inline]
+    pub const fn n_parts(&self) -> usize {
+        self.n_big + self.n_small
+    }
+
+    /// The length of the longer parts at the front.
+    #[inline]
+    pub const fn big_len(&self) -> usize {
+        self.big_len
+    }
+
+    /// The number of longer parts at the front.
+    #[inline]
+    pub const fn n_big(&self) -> usize {
+        self.n_big
+    }
+
+    /// The length of the shorter parts at the back.
+    #[inline]
+    pub const fn small_len(&self) -> usize {
+        self.small_len
+    }
+
+    /// The number of shorter parts at the back.
+    #[inline]
+    pub const fn n_small(&self) -> usize {
+        self.n_small
+    }
+
+    /// The length of part `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i >= self.n_parts()`.
+    #[inline]
+    pub const fn part_len(&self, i: usize) -> usize {
+        assert!(i < self.n_parts(), "part index out of range");
+
+        if i < self.n_big {
+            self.big_len
+        } else {
+            self.small_len
+        }
+    }
+
+    /// The range of elements part `i` covers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i >= self.n_parts()`.
+    #[inline]
+    pub const fn part_range(&self, i: usize) -> Range<usize> {
+        // Before `start_of`, which could overflow for a far-out `i`.
+        assert!(i < self.n_parts(), "part index out of range");
+
+        let start = self.start_of(i);
+        start..start + self.part_len(i)
+    }
+
+    /// The index of the part containing element `idx`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx >= self.len()`.
+    #[inline]
+    pub const fn part_of(&self, idx: usize) -> usize {
+        assert!(idx < self.len(), "element index out of range");
+
+        let big_end = self.n_big * self.big_len;
+        if idx < big_end {
+            idx / self.big_len
+        } else {
+            self.n_big + (idx - big_end) / self.small_len
+        }
+    }
+
+    /// The offsets where each part starts, followed by [`len`].
+    ///
+    /// This yields `n_parts() + 1` offsets, so part `i` is
+    /// `boundaries[i]..boundaries[i + 1]`.
+    ///
+    /// [`len`]: Self::len
+    #[inline]
+    pub fn boundaries(&self) -> impl DoubleEndedIterator<Item = usize> {
+        let layout = *self;
+        (0..self.n_parts())
+            .map(move |i| layout.start_of(i))
+            .chain(core::iter::once(self.len()))
+    }
+
+    /// Splits `slice` according to this layout.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slice.len() != self.len()`.
+    #[inline]
+    pub fn apply<'a, T>(&self, slice: &'a [T]) -> Parts<'a, T> {
+        assert_eq!(
+            slice.len(),
+            self.len(),
+            "slice length does not match the layout"
+        );
+
+        // SAFETY: The layout covers exactly `slice.len()` elements.
+        unsafe { Parts::from_layout_unchecked(slice, *self) }
+    }
+
+    /// Where part `i` starts. `i` may be `n_parts()`.
+    #[inline]
+    const fn start_of(&self, i: usize) -> usize {
+        if i < self.n_big {
+            i * self.big_len
+        } else {
+            self.n_big * self.big_len + (i - self.n_big) * self.small_len
+        }
+    }
+}
+
+const fn checked_len(
+    big_len: usize,
+    n_big: usize,
+    small_len: usize,
+    n_small: usize,
+) -> Option<usize> {
+    match (n_big.checked_mul(big_len), n_small.checked_mul(small_len)) {
+        (Some(big), Some(small)) => big.checked_add(small),
+        _ => None,
+    }
+}
+
+/// An explicit list of part boundaries.
+///
+/// Part `i` covers `boundaries[i]..boundaries[i + 1]`, so unlike
+/// [`PartsLayout`], parts may have any length, including zero.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BoundaryLayout {
+    boundaries: Vec<usize>,
+}
+
+#[cfg(feature = "alloc")]
+impl BoundaryLayout {
+    /// Checks that `boundaries` start at 0 and never decrease.
+    ///
+    /// The last boundary is the number of elements covered.
+    pub fn new(boundaries: Vec<usize>) -> Result<Self, LayoutError> {
+        check_boundaries(&boundaries)?;
+        Ok(Self { boundaries })
+    }
+
+    /// The boundaries, starting at 0 and ending at [`len`].
+    ///
+    /// [`len`]: Self::len
+    #[inline]
+    pub fn boundaries(&self) -> &[usize] {
+        &self.boundaries
+    }
+
+    /// The number of elements this layout covers.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.boundaries[self.boundaries.len() - 1]
+    }
+
+    /// Whether this layout covers no elements. It may still have
+    /// (empty) parts.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of parts.
+    #[inline]
+    pub fn n_parts(&self) -> usize {
+        self.boundaries.len() - 1
+    }
+
+    /// The range of elements part `i` covers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i >= self.n_parts()`.
+    #[inline]
+    pub fn part_range(&self, i: usize) -> Range<usize> {
+        assert!(i < self.n_parts(), "part index out of range");
+        self.boundaries[i]..self.boundaries[i + 1]
+    }
+
+    /// The index of the part containing element `idx`. Empty parts
+    /// contain no elements, so they are never returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx >= self.len()`.
+    pub fn part_of(&self, idx: usize) -> usize {
+        assert!(idx < self.len(), "element index out of range");
+        self.boundaries.partition_point(|&b| b <= idx) - 1
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl BoundaryLayout {
+    /// Splits `slice` at these boundaries.
+    ///
+    /// Fails if `slice.len()` does not match [`len`](Self::len).
+    #[inline]
+    pub fn apply<'a, 'b, T>(
+        &'b self,
+        slice: &'a [T],
+    ) -> Result<explicit::Parts<'a, 'b, T>, LayoutError> {
+        if slice.len() != self.len() {
+            return Err(LayoutError::LengthMismatch {
+                expected: slice.len(),
+                actual: self.len(),
+            });
+        }
+
+        // SAFETY: The boundaries were checked when `self` was built, and
+        // end at `slice.len()`.
+        Ok(unsafe { explicit::Parts::from_boundaries_unchecked(slice, &self.boundaries) })
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<PartsLayout> for BoundaryLayout {
+    fn from(layout: PartsLayout) -> Self {
+        Self {
+            boundaries: layout.boundaries().collect(),
+        }
+    }
+}
+
+/// Checks that `boundaries` start at 0 and never decrease.
+pub(crate) fn check_boundaries(boundaries: &[usize]) -> Result<(), LayoutError> {
+    if boundaries.first() != Some(&0) {
+        return Err(LayoutError::NotStartingAtZero);
+    }
+
+    match boundaries.windows(2).position(|w| w[0] > w[1]) {
+        Some(i) => Err(LayoutError::Decreasing { index: i + 1 }),
+        None => Ok(()),
+    }
+}
+
+/// Why a layout was rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutError {
+    /// The part sizes add up to more than `usize::MAX`.
+    Overflow,
+    /// The part sizes are not the balanced ones [`PartsLayout::new`]
+    /// produces.
+    Unbalanced,
+    /// The boundary list is empty or does not start at 0.
+    NotStartingAtZero,
+    /// `boundaries[index]` is smaller than the boundary before it.
+    Decreasing { index: usize },
+    /// The parts cover `actual` elements, but the slice has `expected`.
+    LengthMismatch { expected: usize, actual: usize },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Overflow => f.write_str("part sizes overflow usize"),
+            Self::Unbalanced => f.write_str("part sizes are not balanced"),
+            Self::NotStartingAtZero => f.write_str("boundaries do not start at 0"),
+            Self::LengthMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "parts cover {actual} elements, but the slice has {expected}"
+                )
+            }
+            Self::Decreasing { index } => {
+                write!(f, "boundary {index} is smaller than the one before")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LayoutError {}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename = "PartsLayout")]
+    struct Sizes {
+        big_len: usize,
+        n_big: usize,
+        small_len: usize,
+        n_small: usize,
+    }
+
+    impl Serialize for PartsLayout {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            Sizes {
+                big_len: self.big_len,
+                n_big: self.n_big,
+                small_len: self.small_len,
+                n_small: self.n_small,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for PartsLayout {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let Sizes {
+                big_len,
+                n_big,
+                small_len,
+                n_small,
+            } = Sizes::deserialize(deserializer)?;
+
+            PartsLayout::from_sizes(big_len, n_big, small_len, n_small).map_err(D::Error::custom)
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl Serialize for BoundaryLayout {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.boundaries.serialize(serializer)
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<'de> Deserialize<'de> for BoundaryLayout {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            BoundaryLayout::new(Vec::deserialize(deserializer)?).map_err(D::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conformance;
+    use std::{
+        collections::hash_map::DefaultHasher,
+        hash::{Hash, Hasher},
+        vec,
+        vec::Vec,
+    };
+
+    #[test]
+    fn matches_parts() {
+        for (items, n) in conformance::cases() {
+            let layout = PartsLayout::new(items.len(), n);
+            let parts: Vec<_> = Parts::new(&items, n).collect();
+
+            assert_eq!(layout.len(), items.len());
+            assert_eq!(layout.n_parts(), parts.len());
+            assert_eq!(layout.apply(&items).collect::<Vec<_>>(), parts);
+
+            let boundaries: Vec<_> = layout.boundaries().collect();
+            assert_eq!(boundaries.len(), parts.len() + 1);
+
+            for (i, part) in parts.iter().enumerate() {
+                let range = layout.part_range(i);
+                assert_eq!(&items[range.clone()], *part);
+                assert_eq!(layout.part_len(i), part.len());
+                assert_eq!(boundaries[i]..boundaries[i + 1], range);
+
+                for idx in range {
+                    assert_eq!(layout.part_of(idx), i);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn zst_at_the_limits() {
+        let layout = PartsLayout::new(usize::MAX, 1);
+        assert_eq!(layout.part_range(0), 0..usize::MAX);
+        assert_eq!(layout.part_of(usize::MAX - 1), 0);
+
+        let layout = PartsLayout::new(usize::MAX, usize::MAX);
+        assert_eq!(layout.n_parts(), usize::MAX);
+        assert_eq!(
+            layout.part_range(usize::MAX - 1),
+            usize::MAX - 1..usize::MAX
+        );
+        assert_eq!(layout.boundaries().next_back(), Some(usize::MAX));
+
+        let layout = PartsLayout::new(usize::MAX, 2);
+        assert_eq!(layout.part_range(1), usize::MAX / 2 + 1..usize::MAX);
+    }
+
+    #[test]
+    fn equal_layouts_hash_equal() {
+        let hash = |layout: PartsLayout| {
+            let mut hasher = DefaultHasher::new();
+            layout.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        // 10 parts of 1 either way.
+        let a = PartsLayout::new(10, 10);
+        let b = PartsLayout::new(10, 20);
+        assert_eq!(a, b);
+        assert_eq!(hash(a), hash(b));
+        assert_ne!(a, PartsLayout::new(10, 3));
+    }
+
+    #[test]
+    #[should_panic = "part index out of range"]
+    fn part_range_far_out_of_range() {
+        PartsLayout::new(usize::MAX, 2).part_range(usize::MAX);
+    }
+
+    #[test]
+    #[should_panic = "slice length does not match the layout"]
+    fn apply_wrong_len() {
+        PartsLayout::new(5, 2).apply(&[0; 4]);
+    }
+
+    #[test]
+    fn from_sizes_round_trips() {
+        for (items, n) in conformance::cases() {
+            let layout = PartsLayout::new(items.len(), n);
+            let rebuilt = PartsLayout::from_sizes(
+                layout.big_len(),
+                layout.n_big(),
+                layout.small_len(),
+                layout.n_small(),
+            );
+            assert_eq!(rebuilt, Ok(layout));
+        }
+    }
+
+    #[test]
+    fn from_sizes_rejects_bad_sizes() {
+        use LayoutError::*;
+
+        // Sizes more than one apart, or in the wrong order.
+        assert_eq!(PartsLayout::from_sizes(5, 1, 3, 1), Err(Unbalanced));
+        assert_eq!(PartsLayout::from_sizes(3, 1, 4, 1), Err(Unbalanced));
+        // Uniform parts must all be big.
+        assert_eq!(PartsLayout::from_sizes(0, 0, 3, 2), Err(Unbalanced));
+        // Leftover sizes without any parts.
+        assert_eq!(PartsLayout::from_sizes(7, 0, 0, 0), Err(Unbalanced));
+        // Empty parts.
+        assert_eq!(PartsLayout::from_sizes(1, 2, 0, 3), Err(Unbalanced));
+
+        assert_eq!(PartsLayout::from_sizes(usize::MAX, 2, 0, 0), Err(Overflow));
+        assert_eq!(
+            PartsLayout::from_sizes(usize::MAX / 2 + 1, 1, usize::MAX / 2, 2),
+            Err(Overflow)
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn boundary_layouts() {
+        use LayoutError::*;
+
+        let layout = BoundaryLayout::new(vec![0, 10, 10, 25]).unwrap();
+        assert_eq!(layout.len(), 25);
+        assert_eq!(layout.n_parts(), 3);
+        assert_eq!(layout.part_range(1), 10..10);
+        assert_eq!(layout.part_of(9), 0);
+        assert_eq!(layout.part_of(10), 2);
+
+        assert_eq!(BoundaryLayout::new(vec![]), Err(NotStartingAtZero));
+        assert_eq!(BoundaryLayout::new(vec![1, 2]), Err(NotStartingAtZero));
+        assert_eq!(
+            BoundaryLayout::new(vec![0, 5, 4, 6]),
+            Err(Decreasing { index: 2 })
+        );
+
+        let balanced = PartsLayout::new(5, 2);
+        assert_eq!(BoundaryLayout::from(balanced).boundaries(), [0, 3, 5]);
+
+        let a = [0, 1, 2, 3, 4];
+        let layout = BoundaryLayout::new(vec![0, 1, 1, 5]).unwrap();
+        let parts: Vec<_> = layout.apply(&a).unwrap().collect();
+        assert_eq!(parts, [&[0][..], &[], &[1, 2, 3, 4]]);
+        assert_eq!(
+            layout.apply(&a[1..]).err(),
+            Some(LengthMismatch {
+                expected: 4,
+                actual: 5
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trips() {
+        let layout = PartsLayout::new(100, 7);
+        let json = serde_json::to_string(&layout).unwrap();
+        assert_eq!(
+            json,
+            r#"{"big_len":15,"n_big":2,"small_len":14,"n_small":5}"#
+        );
+        assert_eq!(serde_json::from_str::<PartsLayout>(&json).unwrap(), layout);
+
+        let layout = BoundaryLayout::from(layout);
+        let json = serde_json::to_string(&layout).unwrap();
+        assert_eq!(json, "[0,15,30,44,58,72,86,100]");
+        assert_eq!(
+            serde_json::from_str::<BoundaryLayout>(&json).unwrap(),
+            layout
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_rejects_corrupt_plans() {
+        // Would claim 3 + 3 + 1 elements, but split as if there were 7
+        // balanced parts.
+        let err = serde_json::from_str::<PartsLayout>(
+            r#"{"big_len":3,"n_big":2,"small_len":1,"n_small":1}"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("not balanced"), "{err}");
+
+        let err = serde_json::from_str::<PartsLayout>(&format!(
+            r#"{{"big_len":{},"n_big":3,"small_len":0,"n_small":0}}"#,
+            usize::MAX / 2
+        ))
+        .unwrap_err();
+        assert!(err.to_string().contains("overflow"), "{err}");
+
+        let err = serde_json::from_str::<BoundaryLayout>("[0,5,3]").unwrap_err();
+        assert!(err.to_string().contains("boundary 2"), "{err}");
+    }
+}
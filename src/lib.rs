@@ -0,0 +1,81 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+pub mod blocks;
+pub mod by_key;
+#[cfg(test)]
+mod conformance;
+pub mod csr;
+pub mod curve;
+pub mod double_chunk;
+pub mod explicit;
+pub mod layout;
+pub mod merge_path;
+pub mod on_demand;
+#[cfg(feature = "std")]
+pub mod par;
+#[cfg(feature = "std")]
+pub mod pool;
+pub mod precomputed;
+#[cfg(feature = "alloc")]
+pub mod quantile;
+#[cfg(feature = "alloc")]
+pub mod repartition;
+pub mod schedule;
+pub mod segments;
+pub mod seri;
+pub mod seri2;
+pub mod seri2_flipped;
+pub mod seri2_slice;
+pub mod seri2_slice_flipped;
+pub mod split;
+pub mod tiles;
+#[cfg(feature = "alloc")]
+pub mod vec_deque;
+
+macro_rules! ext {
+    () => {
+        pub trait PartsExt<T> {
+            /// Returns an iterator that divides the slice into a
+            /// maximum of `n_chunks` chunks, starting at the
+            /// beginning of the slice.
+            ///
+            /// The chunks are slices and do not overlap. If the slice
+            /// cannot be evenly divided into `n_chunks` parts, some
+            /// chunks will be one element longer than others. It is
+            /// not guaranteed which chunks will be longer.
+            ///
+            /// See [`chunks`] for an iterator that returns chunks of
+            /// a specified length, instead of a specified number of
+            /// chunks.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `n_chunks` is 0.
+            ///
+            /// # Examples
+            ///
+            /// ```
+            #[doc = concat!("# use ", module_path!(), "::PartsExt;")]
+            /// let slice = ['l', 'o', 'r', 'e', 'm'];
+            /// let parts: Vec<_> = slice.parts(2).collect();
+            /// assert_eq!(parts.len(), 2);
+            /// assert_eq!(parts.concat(), slice);
+            /// ```
+            ///
+            /// [`chunks_exact`]: slice::chunks_exact
+            /// [`rchunks`]: slice::rchunks
+
+            fn parts(&self, n_chunks: usize) -> Parts<'_, T>;
+        }
+
+        impl<T> PartsExt<T> for [T] {
+            fn parts(&self, n_chunks: usize) -> Parts<'_, T> {
+                Parts::new(self, n_chunks)
+            }
+        }
+    };
+}
+pub(crate) use ext;
@@ -0,0 +1,236 @@
+//! Splitting two sorted slices into pairs that can be merged
+//! independently, for merging in parallel.
+//!
+//! Each pair is found with the merge path search: the `d`-th element of
+//! the merged output splits `a` and `b` at `i` and `d - i`, and `i` can
+//! be found by binary search along that diagonal.
+
+use core::cmp::Ordering;
+
+use crate::layout::PartsLayout;
+
+/// Splits sorted `a` and `b` into a maximum of `n_parts` pairs
+/// `(a_part, b_part)`.
+///
+/// Merging each pair and concatenating the results gives the same
+/// output as merging `a` and `b` whole, with elements of `a` going
+/// first among equals. The combined length of each pair follows the
+/// balanced layout of `a.len() + b.len()` elements.
+///
+/// # Panics
+///
+/// Panics if `n_parts` is 0, or if `a.len() + b.len()` overflows.
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::merge_path::merge_path_parts;
+///
+/// let a = [1, 3, 5, 7];
+/// let b = [2, 4, 6];
+/// let parts: Vec<_> = merge_path_parts(&a, &b, 2, Ord::cmp).collect();
+/// assert_eq!(parts, [(&[1, 3][..], &[2, 4][..]), (&[5, 7][..], &[6][..])]);
+/// ```
+pub fn merge_path_parts<'a, T, F>(
+    a: &'a [T],
+    b: &'a [T],
+    n_parts: usize,
+    cmp: F,
+) -> MergeParts<'a, T, F>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let len = a
+        .len()
+        .checked_add(b.len())
+        .expect("combined length overflows usize");
+    let layout = PartsLayout::new(len, n_parts);
+
+    MergeParts {
+        a,
+        b,
+        layout,
+        front: 0,
+        back: layout.n_parts(),
+        cmp,
+    }
+}
+
+/// Finds where the first `diagonal` elements of the merge of `a` and
+/// `b` come from: `a[..i]` and `b[..diagonal - i]`.
+///
+/// Elements of `a` go first among equals.
+///
+/// # Panics
+///
+/// Panics if `diagonal > a.len() + b.len()`.
+pub fn merge_path_split<T, F>(a: &[T], b: &[T], diagonal: usize, mut cmp: F) -> (usize, usize)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    assert!(
+        diagonal - a.len().min(diagonal) <= b.len(),
+        "diagonal out of range"
+    );
+
+    let mut lo = diagonal.saturating_sub(b.len());
+    let mut hi = diagonal.min(a.len());
+
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        // `lo <= mid < hi` keeps both indices in bounds.
+        if cmp(&a[mid], &b[diagonal - mid - 1]) != Ordering::Greater {
+            // a[mid] is merged before b[diagonal - mid - 1].
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+
+    (lo, diagonal - lo)
+}
+
+/// Iterator returned by [`merge_path_parts`].
+pub struct MergeParts<'a, T, F> {
+    a: &'a [T],
+    b: &'a [T],
+    layout: PartsLayout,
+    front: usize,
+    back: usize,
+    cmp: F,
+}
+
+impl<'a, T, F> MergeParts<'a, T, F>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    fn pair(&mut self, part: usize) -> (&'a [T], &'a [T]) {
+        let range = self.layout.part_range(part);
+        let (a_start, b_start) = merge_path_split(self.a, self.b, range.start, &mut self.cmp);
+        let (a_end, b_end) = merge_path_split(self.a, self.b, range.end, &mut self.cmp);
+
+        (&self.a[a_start..a_end], &self.b[b_start..b_end])
+    }
+}
+
+impl<'a, T, F> Iterator for MergeParts<'a, T, F>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    type Item = (&'a [T], &'a [T]);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        self.front += 1;
+        Some(self.pair(self.front - 1))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T, F> DoubleEndedIterator for MergeParts<'_, T, F>
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        self.back -= 1;
+        Some(self.pair(self.back))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::vec::Vec;
+
+    /// Sorted `(key, tag)` pairs with plenty of duplicate keys.
+    fn sorted(seed: u64, len: usize, tag: u8) -> Vec<(u8, u8)> {
+        let mut state = seed;
+        let mut v: Vec<_> = (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                ((state >> 59) as u8, tag)
+            })
+            .collect();
+        v.sort();
+        v
+    }
+
+    fn merge(a: &[(u8, u8)], b: &[(u8, u8)]) -> Vec<(u8, u8)> {
+        let mut out: Vec<_> = a.iter().chain(b).copied().collect();
+        // Stable, so elements of `a` stay first among equal keys.
+        out.sort_by_key(|&(key, _)| key);
+        out
+    }
+
+    #[test]
+    fn pairs_merge_independently() {
+        let by_key = |x: &(u8, u8), y: &(u8, u8)| x.0.cmp(&y.0);
+
+        for a_len in 0..24 {
+            for b_len in 0..24 {
+                let a = sorted(a_len as u64, a_len, 0);
+                let b = sorted(!(b_len as u64), b_len, 1);
+                let expected = merge(&a, &b);
+
+                for n in 1..=a_len + b_len + 2 {
+                    let ctx = format!("a_len={a_len}, b_len={b_len}, n={n}");
+                    let layout = PartsLayout::new(a_len + b_len, n);
+                    let pairs: Vec<_> = merge_path_parts(&a, &b, n, by_key).collect();
+                    assert_eq!(pairs.len(), layout.n_parts(), "{ctx}");
+
+                    let mut merged = Vec::new();
+                    let (mut a_pos, mut b_pos) = (0, 0);
+                    for (i, (a_part, b_part)) in pairs.iter().enumerate() {
+                        assert_eq!(a_part.as_ptr(), a[a_pos..].as_ptr(), "{ctx}");
+                        assert_eq!(b_part.as_ptr(), b[b_pos..].as_ptr(), "{ctx}");
+                        assert_eq!(a_part.len() + b_part.len(), layout.part_len(i), "{ctx}");
+                        a_pos += a_part.len();
+                        b_pos += b_part.len();
+
+                        merged.extend(merge(a_part, b_part));
+                    }
+                    assert_eq!((a_pos, b_pos), (a_len, b_len), "{ctx}");
+                    assert_eq!(merged, expected, "{ctx}");
+
+                    let mut rev: Vec<_> = merge_path_parts(&a, &b, n, by_key).rev().collect();
+                    rev.reverse();
+                    assert_eq!(rev, pairs, "{ctx}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn one_side_empty() {
+        let a = [1, 2, 3, 4, 5];
+        let parts: Vec<_> = merge_path_parts(&a, &[], 2, Ord::cmp).collect();
+        assert_eq!(parts, [(&a[..3], &[][..]), (&a[3..], &[][..])]);
+
+        let parts: Vec<_> = merge_path_parts(&[], &a, 2, Ord::cmp).collect();
+        assert_eq!(parts, [(&[][..], &a[..3]), (&[][..], &a[3..])]);
+    }
+
+    #[test]
+    fn equal_keys_take_a_first() {
+        let a = [1, 1, 1];
+        let b = [1, 1, 1];
+        assert_eq!(merge_path_split(&a, &b, 4, Ord::cmp), (3, 1));
+        assert_eq!(merge_path_split(&a, &b, 2, Ord::cmp), (2, 0));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::split::split_at_unchecked;
+
 super::ext!();
 
 pub struct Parts<'a, T> {
@@ -17,7 +19,7 @@ impl<'a, T> Iterator for Parts<'a, T> {
     fn next(&mut self) -> Option<Self::Item> {
         let Self { slice, n_parts } = *self;
 
-        if n_parts == 0 {
+        if n_parts == 0 || slice.is_empty() {
             return None;
         }
 
@@ -31,10 +33,17 @@ impl<'a, T> Iterator for Parts<'a, T> {
         // Or on nightly
         // let chunk_size = slice.len().div_ceil(n_parts);
 
-        let (head, tail) = unsafe { slice.split_at_unchecked(chunk_size) };
+        let (head, tail) = unsafe { split_at_unchecked(slice, chunk_size) };
         self.slice = tail;
         self.n_parts -= 1;
 
         Some(head)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    crate::conformance::conformance_tests!();
+}
@@ -0,0 +1,890 @@
+//! Running a closure on every part of a slice in parallel, on scoped
+//! threads.
+//!
+//! Part 0 runs on the calling thread and every other part gets a thread
+//! of its own, named `part-{index}`. If parts panic, the panic of the
+//! first of them is propagated as a [`PartPanic`] once all parts are
+//! done.
+
+use std::{
+    any::Any,
+    fmt,
+    mem::{self, MaybeUninit},
+    ops::Range,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    vec::Vec,
+};
+
+use crate::{layout::PartsLayout, seri2_slice};
+
+/// Calls `f(index, part)` on every part of `slice`, split into a maximum
+/// of `n_parts` parts, in parallel.
+///
+/// # Panics
+///
+/// Panics if `n_parts` is 0, or with a [`PartPanic`] if `f` panics.
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::par::for_each_part;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// let sum = AtomicUsize::new(0);
+/// for_each_part(&[1, 2, 3, 4, 5], 2, |_, part| {
+///     sum.fetch_add(part.iter().sum(), Ordering::Relaxed);
+/// });
+/// assert_eq!(sum.into_inner(), 15);
+/// ```
+pub fn for_each_part<T, F>(slice: &[T], n_parts: usize, f: F)
+where
+    T: Sync,
+    F: Fn(usize, &[T]) + Sync,
+{
+    run(seri2_slice::Parts::new(slice, n_parts), &f);
+}
+
+/// Like [`for_each_part`], but with mutable parts.
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::par::for_each_part_mut;
+///
+/// let mut v = [0; 5];
+/// for_each_part_mut(&mut v, 2, |i, part| part.fill(i));
+/// assert_eq!(v, [0, 0, 0, 1, 1]);
+/// ```
+pub fn for_each_part_mut<T, F>(slice: &mut [T], n_parts: usize, f: F)
+where
+    T: Send,
+    F: Fn(usize, &mut [T]) + Sync,
+{
+    run(parts_mut(slice, n_parts), &f);
+}
+
+/// Calls `f(index, part)` on every part of `slice`, split into a maximum
+/// of `n_parts` parts, in parallel, and returns the results in part
+/// order.
+///
+/// # Panics
+///
+/// Panics if `n_parts` is 0, or with a [`PartPanic`] if `f` panics.
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::par::map_parts;
+///
+/// let sums = map_parts(&[1, 2, 3, 4, 5], 2, |_, part| part.iter().sum::<i32>());
+/// assert_eq!(sums, [6, 9]);
+/// ```
+pub fn map_parts<T, R, F>(slice: &[T], n_parts: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(usize, &[T]) -> R + Sync,
+{
+    run(seri2_slice::Parts::new(slice, n_parts), &f)
+}
+
+/// Like [`map_parts`], but with mutable parts.
+pub fn map_parts_mut<T, R, F>(slice: &mut [T], n_parts: usize, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(usize, &mut [T]) -> R + Sync,
+{
+    run(parts_mut(slice, n_parts), &f)
+}
+
+/// Folds every part of `slice`, split into a maximum of `n_parts`
+/// parts, in parallel, then combines the per-part results in part
+/// order.
+///
+/// Each part is folded with `fold`, starting from `identity()`. The
+/// results are combined from left to right, so `combine` has to be
+/// associative but need not be commutative. An empty slice gives
+/// `identity()`.
+///
+/// # Panics
+///
+/// Panics if `n_parts` is 0, or with a [`PartPanic`] if `fold` panics.
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::par::par_reduce;
+///
+/// let words = ["sheps", "-", "chunk", "erino"];
+/// let joined = par_reduce(
+///     &words,
+///     3,
+///     String::new,
+///     |acc, word| acc + word,
+///     |left, right| left + &right,
+/// );
+/// assert_eq!(joined, "sheps-chunkerino");
+/// ```
+pub fn par_reduce<T, A, I, F, C>(slice: &[T], n_parts: usize, identity: I, fold: F, combine: C) -> A
+where
+    T: Sync,
+    A: Send,
+    I: Fn() -> A + Sync,
+    F: Fn(A, &T) -> A + Sync,
+    C: Fn(A, A) -> A,
+{
+    map_parts(slice, n_parts, |_, part| {
+        part.iter().fold(identity(), &fold)
+    })
+    .into_iter()
+    .reduce(combine)
+    .unwrap_or_else(identity)
+}
+
+/// Replaces every element of `slice` with `op` applied to it and all
+/// elements before it, an inclusive prefix scan, in parallel.
+///
+/// This is the classic two-pass scan: every part is scanned on its own,
+/// the part totals are scanned to find the carry into each part, and
+/// the carries are applied in a second parallel pass. `op` has to be
+/// associative, and is called about twice per element.
+///
+/// # Panics
+///
+/// Panics if `n_parts` is 0, or with a [`PartPanic`] if `op` panics.
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::par::par_scan_inplace;
+///
+/// let mut v = [1, 2, 3, 4, 5];
+/// par_scan_inplace(&mut v, 2, |a, b| a + b);
+/// assert_eq!(v, [1, 3, 6, 10, 15]);
+/// ```
+pub fn par_scan_inplace<T, F>(slice: &mut [T], n_parts: usize, op: F)
+where
+    T: Clone + Send + Sync,
+    F: Fn(&T, &T) -> T + Sync,
+{
+    let totals = map_parts_mut(slice, n_parts, |_, part| {
+        for i in 1..part.len() {
+            part[i] = op(&part[i - 1], &part[i]);
+        }
+        part.last().cloned()
+    });
+
+    // The carry into part `k` is the scan of the totals before it.
+    let mut carries = Vec::with_capacity(totals.len());
+    let mut carry: Option<T> = None;
+    for total in totals.into_iter().flatten() {
+        carries.push(carry.clone());
+        carry = Some(match carry {
+            Some(carry) => op(&carry, &total),
+            None => total,
+        });
+    }
+
+    // Part 0 has no carry, so it is left out of the second pass instead
+    // of getting a thread of its own.
+    run_indexed(
+        parts_mut(slice, n_parts).zip(carries).enumerate().skip(1),
+        &|_, (part, carry)| {
+            if let Some(carry) = carry {
+                for x in part {
+                    *x = op(&carry, x);
+                }
+            }
+        },
+    );
+}
+
+/// Returns the index of the first element of `slice` that matches
+/// `pred`, searching the parts of `slice`, split into a maximum of
+/// `n_parts` parts, in parallel.
+///
+/// The result is the same as that of [`Iterator::position`]. Parts
+/// share the lowest match found so far, and a part stops early once a
+/// match before it is known. `pred` may still be called on elements
+/// after the first match.
+///
+/// # Panics
+///
+/// Panics if `n_parts` is 0, or with a [`PartPanic`] if `pred` panics.
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::par::par_position;
+///
+/// let v: Vec<u32> = (0..1000).collect();
+/// assert_eq!(par_position(&v, 4, |&x| x > 0 && x % 300 == 0), Some(300));
+/// assert_eq!(par_position(&v, 4, |&x| x > 1000), None);
+/// ```
+pub fn par_position<T, P>(slice: &[T], n_parts: usize, pred: P) -> Option<usize>
+where
+    T: Sync,
+    P: Fn(&T) -> bool + Sync,
+{
+    search(slice, n_parts, &pred, true)
+}
+
+/// Returns whether any element of `slice` matches `pred`, searching the
+/// parts of `slice`, split into a maximum of `n_parts` parts, in
+/// parallel. All parts stop early once a match is found.
+///
+/// # Panics
+///
+/// Panics if `n_parts` is 0, or with a [`PartPanic`] if `pred` panics.
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::par::par_any;
+///
+/// let v: Vec<u32> = (0..1000).collect();
+/// assert!(par_any(&v, 4, |&x| x == 999));
+/// assert!(!par_any(&v, 4, |&x| x == 1000));
+/// ```
+pub fn par_any<T, P>(slice: &[T], n_parts: usize, pred: P) -> bool
+where
+    T: Sync,
+    P: Fn(&T) -> bool + Sync,
+{
+    search(slice, n_parts, &pred, false).is_some()
+}
+
+/// Builds a `Vec` of `len` elements by calling `f(index, range, writer)`
+/// on every part of its uninitialized buffer, split into a maximum of
+/// `n_parts` parts, in parallel.
+///
+/// `range` is where the part sits in the output, following the
+/// `seri2_slice` layout, and `f` fills it in order through `writer`.
+/// The `Vec` is only returned once every part is full. If a part panics
+/// or leaves elements unwritten, every element written so far, in any
+/// part, is dropped before the panic is propagated.
+///
+/// # Panics
+///
+/// Panics if `n_parts` is 0 or if `len` elements do not fit in a `Vec`.
+/// Panics with a [`PartPanic`] if `f` panics, or if it returns before
+/// filling its part.
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::par::par_init;
+///
+/// let squares: Vec<u64> = par_init(1000, 4, |_, range, writer| {
+///     writer.extend(range.map(|i| (i * i) as u64));
+/// });
+/// assert_eq!(squares[999], 998_001);
+/// ```
+pub fn par_init<T, F>(len: usize, n_parts: usize, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize, Range<usize>, &mut PartWriter<'_, T>) + Sync,
+{
+    let layout = PartsLayout::new(len, n_parts);
+    let mut v = Vec::with_capacity(len);
+
+    let results = run(
+        parts_mut(&mut v.spare_capacity_mut()[..len], n_parts),
+        &|i, part| {
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                let mut writer = PartWriter::new(part);
+                f(i, layout.part_range(i), &mut writer);
+                writer.finish(i);
+            }))
+        },
+    );
+
+    if results.iter().all(Result::is_ok) {
+        // SAFETY: Every part was checked to be full.
+        unsafe { v.set_len(len) };
+        return v;
+    }
+
+    // The writers of the failed parts dropped what they held, so only
+    // the full parts are left.
+    let mut panicked = None;
+    for (i, result) in results.into_iter().enumerate() {
+        match result {
+            Ok(()) => {
+                let range = layout.part_range(i);
+                // SAFETY: Part `i` was checked to be full, and nothing
+                // else refers to its elements.
+                unsafe {
+                    let part = v.as_mut_ptr().add(range.start);
+                    ptr::drop_in_place(ptr::slice_from_raw_parts_mut(part, range.len()));
+                }
+            }
+            Err(payload) => {
+                panicked.get_or_insert(PartPanic::new(i, payload));
+            }
+        }
+    }
+
+    let part_panic = panicked.expect("a part panicked");
+    panic::resume_unwind(Box::new(part_panic));
+}
+
+/// Fills one part of the buffer of [`par_init`] from the front, and
+/// keeps track of how much of it is initialized.
+///
+/// If the part does not finish, the writer drops the elements it has
+/// written.
+pub struct PartWriter<'a, T> {
+    buf: &'a mut [MaybeUninit<T>],
+    /// The length of the initialized prefix of `buf`.
+    filled: usize,
+}
+
+impl<'a, T> PartWriter<'a, T> {
+    #[inline]
+    fn new(buf: &'a mut [MaybeUninit<T>]) -> Self {
+        Self { buf, filled: 0 }
+    }
+
+    /// Writes `value` after the elements written so far.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the part is already full.
+    #[inline]
+    pub fn push(&mut self, value: T) {
+        assert!(!self.is_full(), "part is already full");
+        self.buf[self.filled].write(value);
+        self.filled += 1;
+    }
+
+    /// The number of elements written so far.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.filled
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.filled == 0
+    }
+
+    /// The number of elements the part holds once it is full.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.filled == self.buf.len()
+    }
+
+    /// Hands the elements over to the output, checking that the part is
+    /// full.
+    fn finish(self, index: usize) {
+        assert!(
+            self.is_full(),
+            "part {index} wrote {} of {} elements",
+            self.filled,
+            self.buf.len(),
+        );
+        mem::forget(self);
+    }
+}
+
+impl<T> Extend<T> for PartWriter<'_, T> {
+    /// # Panics
+    ///
+    /// Panics if `iter` has more elements than fit in the part.
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|value| self.push(value));
+    }
+}
+
+impl<T> Drop for PartWriter<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: The first `filled` elements were written by `push`,
+        // and nothing else will drop them.
+        unsafe {
+            let filled = self.buf.as_mut_ptr().cast::<T>();
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(filled, self.filled));
+        }
+    }
+}
+
+/// How many elements a part searches between looks at the shared best
+/// index.
+const SEARCH_BLOCK: usize = 1024;
+
+/// Searches the parts of `slice` for a match of `pred`. With `first`,
+/// returns the lowest matching index; otherwise returns any of them.
+fn search<T, P>(slice: &[T], n_parts: usize, pred: &P, first: bool) -> Option<usize>
+where
+    T: Sync,
+    P: Fn(&T) -> bool + Sync,
+{
+    let layout = PartsLayout::new(slice.len(), n_parts);
+    // No index reaches `usize::MAX`, so it stands for no match yet.
+    let best = AtomicUsize::new(usize::MAX);
+
+    run(seri2_slice::Parts::new(slice, n_parts), &|i, part: &[T]| {
+        let start = layout.part_range(i).start;
+        // A match below this is one that this part cannot beat.
+        let beaten_by = if first { start } else { usize::MAX };
+
+        for (b, block) in part.chunks(SEARCH_BLOCK).enumerate() {
+            if best.load(Ordering::Relaxed) < beaten_by {
+                return;
+            }
+            if let Some(j) = block.iter().position(pred) {
+                best.fetch_min(start + b * SEARCH_BLOCK + j, Ordering::Relaxed);
+                return;
+            }
+        }
+    });
+
+    let best = best.into_inner();
+    (best != usize::MAX).then_some(best)
+}
+
+/// The panic payload of a part that panicked.
+///
+/// Catch it with [`std::panic::catch_unwind`] and downcast the payload
+/// to find out which part panicked and why.
+///
+/// ```
+/// use sheps_chunkerino::par::{for_each_part, PartPanic};
+/// use std::panic;
+///
+/// let payload = panic::catch_unwind(|| {
+///     for_each_part(&[1, 2, 3], 3, |_, part| assert_ne!(part, [2]));
+/// })
+/// .unwrap_err();
+/// let part_panic = payload.downcast::<PartPanic>().unwrap();
+/// assert_eq!(part_panic.index(), 1);
+/// ```
+pub struct PartPanic {
+    index: usize,
+    payload: Box<dyn Any + Send>,
+}
+
+impl PartPanic {
+    #[inline]
+    pub(crate) fn new(index: usize, payload: Box<dyn Any + Send>) -> Self {
+        Self { index, payload }
+    }
+
+    /// The index of the part that panicked.
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The panic message, if the part panicked with one.
+    pub fn message(&self) -> Option<&str> {
+        match self.payload.downcast_ref::<&'static str>() {
+            Some(s) => Some(s),
+            None => self.payload.downcast_ref::<String>().map(String::as_str),
+        }
+    }
+
+    /// The original panic payload.
+    #[inline]
+    pub fn into_payload(self) -> Box<dyn Any + Send> {
+        self.payload
+    }
+}
+
+impl fmt::Debug for PartPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PartPanic")
+            .field("index", &self.index)
+            .field("message", &self.message())
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for PartPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.message() {
+            Some(message) => write!(f, "part {} panicked: {message}", self.index),
+            None => write!(f, "part {} panicked", self.index),
+        }
+    }
+}
+
+/// The parts of `slice` in the `seri2_slice` layout, mutably.
+pub(crate) fn parts_mut<T>(slice: &mut [T], n_parts: usize) -> impl Iterator<Item = &mut [T]> {
+    let layout = PartsLayout::new(slice.len(), n_parts);
+    let mut rest = slice;
+
+    (0..layout.n_parts()).map(move |i| {
+        let (part, tail) = mem::take(&mut rest).split_at_mut(layout.part_len(i));
+        rest = tail;
+        part
+    })
+}
+
+/// Runs `f` on every part, the first on this thread, and collects the
+/// results in order.
+pub(crate) fn run<P, R, F>(parts: impl Iterator<Item = P>, f: &F) -> Vec<R>
+where
+    P: Send,
+    R: Send,
+    F: Fn(usize, P) -> R + Sync,
+{
+    run_indexed(parts.enumerate(), f)
+}
+
+/// Like [`run`], but with the index of each part given alongside it, for
+/// running only some of the parts.
+pub(crate) fn run_indexed<P, R, F>(parts: impl Iterator<Item = (usize, P)>, f: &F) -> Vec<R>
+where
+    P: Send,
+    R: Send,
+    F: Fn(usize, P) -> R + Sync,
+{
+    thread::scope(|s| {
+        let mut parts = parts;
+        let first = parts.next();
+
+        let handles: Vec<_> = parts
+            .map(|(i, part)| {
+                let handle = thread::Builder::new()
+                    .name(format!("part-{i}"))
+                    .spawn_scoped(s, move || f(i, part))
+                    .expect("failed to spawn thread");
+                (i, handle)
+            })
+            .collect();
+
+        let first =
+            first.map(|(i, part)| (i, panic::catch_unwind(AssertUnwindSafe(|| f(i, part)))));
+
+        let mut results = Vec::with_capacity(handles.len() + 1);
+        let mut panicked = None;
+        for (i, result) in first
+            .into_iter()
+            .chain(handles.into_iter().map(|(i, h)| (i, h.join())))
+        {
+            match result {
+                Ok(r) => results.push(r),
+                Err(payload) => {
+                    panicked.get_or_insert(PartPanic::new(i, payload));
+                }
+            }
+        }
+
+        if let Some(part_panic) = panicked {
+            panic::resume_unwind(Box::new(part_panic));
+        }
+
+        results
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{sync::Mutex, thread::ThreadId, vec};
+
+    #[test]
+    fn results_in_part_order() {
+        for len in 0..40 {
+            let items: Vec<_> = (0..len).collect();
+            for n in 1..=len + 2 {
+                let expected: Vec<_> = seri2_slice::Parts::new(&items, n)
+                    .map(<[_]>::to_vec)
+                    .collect();
+
+                let parts = map_parts(&items, n, |i, part| (i, part.to_vec()));
+                let parts: Vec<_> = parts
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (index, part))| {
+                        assert_eq!(i, index);
+                        part
+                    })
+                    .collect();
+                assert_eq!(parts, expected, "len={len}, n={n}");
+
+                let mut copy = items.clone();
+                let lens = map_parts_mut(&mut copy, n, |i, part| {
+                    part.iter_mut().for_each(|x| *x = i);
+                    part.len()
+                });
+                let expected_lens: Vec<_> = expected.iter().map(Vec::len).collect();
+                assert_eq!(lens, expected_lens, "len={len}, n={n}");
+                let owners: Vec<_> = expected
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, p)| vec![i; p.len()])
+                    .collect();
+                assert_eq!(copy, owners, "len={len}, n={n}");
+            }
+        }
+    }
+
+    #[test]
+    fn first_part_runs_here() {
+        let here = thread::current().id();
+        let ids: Mutex<Vec<(usize, ThreadId)>> = Mutex::new(Vec::new());
+
+        for_each_part(&[0; 8], 4, |i, _| {
+            ids.lock().unwrap().push((i, thread::current().id()));
+        });
+
+        let mut ids = ids.into_inner().unwrap();
+        ids.sort_by_key(|&(i, _)| i);
+        assert_eq!(ids[0].1, here);
+        for &(_, id) in &ids[1..] {
+            assert_ne!(id, here);
+        }
+    }
+
+    #[test]
+    fn propagates_the_first_panic() {
+        let finished = AtomicUsize::new(0);
+        let payload = panic::catch_unwind(|| {
+            for_each_part_mut(&mut [0; 10], 5, |i, _| {
+                if i == 2 || i == 4 {
+                    panic!("part {i} is broken");
+                }
+                finished.fetch_add(1, Ordering::Relaxed);
+            })
+        })
+        .unwrap_err();
+
+        let part_panic = payload.downcast::<PartPanic>().unwrap();
+        assert_eq!(part_panic.index(), 2);
+        assert_eq!(part_panic.message(), Some("part 2 is broken"));
+        assert_eq!(part_panic.to_string(), "part 2 panicked: part 2 is broken");
+        // The other parts still ran to completion.
+        assert_eq!(finished.into_inner(), 3);
+
+        let payload =
+            panic::catch_unwind(|| map_parts(&[0; 3], 3, |i, _| assert_ne!(i, 0))).unwrap_err();
+        assert_eq!(payload.downcast::<PartPanic>().unwrap().index(), 0);
+    }
+
+    #[test]
+    fn reduce_matches_fold() {
+        for len in 0..40 {
+            let items: Vec<u64> = (0..len).map(|i| i * i + 1).collect();
+            for n in 1..=len as usize + 2 {
+                let sum = par_reduce(&items, n, || 0, |a, &x| a + x, |a, b| a + b);
+                assert_eq!(sum, items.iter().sum::<u64>(), "len={len}, n={n}");
+
+                // Not commutative: the parts have to be combined in order.
+                let digits = par_reduce(
+                    &items,
+                    n,
+                    String::new,
+                    |acc, x| acc + &x.to_string() + ",",
+                    |a, b| a + &b,
+                );
+                let expected: String = items.iter().map(|x| format!("{x},")).collect();
+                assert_eq!(digits, expected, "len={len}, n={n}");
+            }
+        }
+    }
+
+    #[test]
+    fn scan_matches_sequential() {
+        for len in 0..40 {
+            let items: Vec<u64> = (0..len).map(|i| i * 7 % 11).collect();
+            let mut expected = items.clone();
+            for i in 1..expected.len() {
+                expected[i] += expected[i - 1];
+            }
+
+            for n in 1..=len as usize + 2 {
+                let mut v = items.clone();
+                par_scan_inplace(&mut v, n, |a, b| a + b);
+                assert_eq!(v, expected, "len={len}, n={n}");
+
+                // 2x2 matrix products are associative but not commutative.
+                let matrices: Vec<[u64; 4]> = items.iter().map(|&x| [1, x, 0, 1 + x % 2]).collect();
+                let mul = |a: &[u64; 4], b: &[u64; 4]| {
+                    [
+                        a[0] * b[0] + a[1] * b[2],
+                        a[0] * b[1] + a[1] * b[3],
+                        a[2] * b[0] + a[3] * b[2],
+                        a[2] * b[1] + a[3] * b[3],
+                    ]
+                    .map(|x| x % 1_000_003)
+                };
+                let mut expected = matrices.clone();
+                for i in 1..expected.len() {
+                    expected[i] = mul(&expected[i - 1], &expected[i]);
+                }
+                let mut v = matrices;
+                par_scan_inplace(&mut v, n, mul);
+                assert_eq!(v, expected, "len={len}, n={n}");
+            }
+        }
+    }
+
+    #[test]
+    fn scan_reports_the_right_part() {
+        // The totals are 3, 3 and 4, so only the second pass over part 2
+        // adds its carry of 6 to an element below 4.
+        let payload = panic::catch_unwind(|| {
+            par_scan_inplace(&mut [1, 1, 1, 1, 1, 1, 1, 1, 2], 3, |a, b| {
+                assert!(*a < 6 || *b >= 4, "part 2 is broken");
+                a + b
+            })
+        })
+        .unwrap_err();
+        assert_eq!(payload.downcast::<PartPanic>().unwrap().index(), 2);
+    }
+
+    #[test]
+    fn position_matches_sequential() {
+        for len in 0..30 {
+            let items: Vec<_> = (0..len).map(|i| i * 5 % 7).collect();
+            for n in 1..=len + 2 {
+                // 7 never matches.
+                for target in [0, 3, 6, 7] {
+                    let pred = |&x: &usize| x == target;
+                    let expected = items.iter().position(pred);
+                    let ctx = format!("len={len}, n={n}, target={target}");
+
+                    assert_eq!(par_position(&items, n, pred), expected, "{ctx}");
+                    assert_eq!(par_any(&items, n, pred), expected.is_some(), "{ctx}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn position_across_blocks() {
+        let len = 10 * SEARCH_BLOCK + 7;
+        for hits in [
+            vec![len - 1],
+            vec![SEARCH_BLOCK, len - 1],
+            vec![3 * SEARCH_BLOCK - 1, 3 * SEARCH_BLOCK, 9 * SEARCH_BLOCK],
+        ] {
+            let mut items = vec![false; len];
+            hits.iter().for_each(|&h| items[h] = true);
+            for n in [1, 2, 3, 7, 16] {
+                assert_eq!(
+                    par_position(&items, n, |&x| x),
+                    Some(hits[0]),
+                    "{hits:?}, n={n}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn search_stops_early() {
+        let len = 1 << 20;
+        let items = vec![0u8; len];
+
+        let calls = AtomicUsize::new(0);
+        let found = par_position(&items, 4, |_| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            true
+        });
+        assert_eq!(found, Some(0));
+        // Parts stop at their first element, or before it once part 0
+        // has found its match.
+        assert!(calls.into_inner() <= 4);
+
+        let calls = AtomicUsize::new(0);
+        let found = par_any(&items, 4, |_| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            true
+        });
+        assert!(found);
+        // Likewise, whichever part publishes its match first.
+        assert!(calls.into_inner() <= 4);
+    }
+
+    #[test]
+    fn init_follows_the_layout() {
+        for len in 0..40 {
+            for n in 1..=len + 2 {
+                let v: Vec<(usize, usize)> = par_init(len, n, |i, range, writer| {
+                    assert_eq!(range.len(), writer.capacity());
+                    writer.extend(range.map(|j| (i, j)));
+                    assert!(writer.is_full());
+                });
+
+                let expected: Vec<_> = PartsLayout::new(len, n)
+                    .boundaries()
+                    .collect::<Vec<_>>()
+                    .windows(2)
+                    .enumerate()
+                    .flat_map(|(i, w)| (w[0]..w[1]).map(move |j| (i, j)))
+                    .collect();
+                assert_eq!(v, expected, "len={len}, n={n}");
+            }
+        }
+
+        let v: Vec<()> = par_init(1000, 3, |_, range, writer| writer.extend(range.map(|_| ())));
+        assert_eq!(v.len(), 1000);
+    }
+
+    #[derive(Debug)]
+    struct Counted<'a>(&'a AtomicUsize);
+
+    impl Drop for Counted<'_> {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn init_drops_everything_on_panic() {
+        let drops = AtomicUsize::new(0);
+        let payload = panic::catch_unwind(|| {
+            par_init(10, 4, |i, range, writer| {
+                writer.extend(range.map(|_| Counted(&drops)));
+                assert_ne!(i, 2, "part 2 is broken");
+            })
+        })
+        .unwrap_err();
+
+        assert_eq!(payload.downcast::<PartPanic>().unwrap().index(), 2);
+        // Parts 0 to 3 hold 3 + 3 + 2 + 2 elements.
+        assert_eq!(drops.into_inner(), 10);
+    }
+
+    #[test]
+    fn init_checks_parts_are_full() {
+        let drops = AtomicUsize::new(0);
+        let payload = panic::catch_unwind(|| {
+            par_init(10, 4, |i, range, writer| {
+                let skip = usize::from(i == 1);
+                writer.extend(range.skip(skip).map(|_| Counted(&drops)));
+            })
+        })
+        .unwrap_err();
+
+        let part_panic = payload.downcast::<PartPanic>().unwrap();
+        assert_eq!(part_panic.index(), 1);
+        assert_eq!(part_panic.message(), Some("part 1 wrote 2 of 3 elements"));
+        assert_eq!(drops.into_inner(), 9);
+
+        let drops = AtomicUsize::new(0);
+        let payload = panic::catch_unwind(|| {
+            par_init(10, 4, |_, range, writer| {
+                writer.extend(range.map(|_| Counted(&drops)));
+                writer.push(Counted(&drops));
+            })
+        })
+        .unwrap_err();
+
+        assert_eq!(payload.downcast::<PartPanic>().unwrap().index(), 0);
+        // The extra element of every part is dropped as `push` panics.
+        assert_eq!(drops.into_inner(), 14);
+    }
+}
@@ -0,0 +1,710 @@
+//! A fixed set of parked worker threads for running closures on the
+//! parts of a slice over and over, without spawning threads each time.
+//!
+//! Every call blocks until all parts are done, so consecutive calls
+//! never overlap. For work in several phases within one call,
+//! [`PartsPool::for_each_part_phased`] hands each part a [`PoolBarrier`]
+//! to wait for the other parts at. The calling thread is worker 0 and
+//! runs a part itself; the other workers sleep on a condition variable
+//! between calls.
+
+use std::{
+    cell::UnsafeCell,
+    mem::{self, MaybeUninit},
+    panic::{self, AssertUnwindSafe},
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+    thread::{self, JoinHandle},
+    vec::Vec,
+};
+
+use crate::{
+    par::{parts_mut, PartPanic},
+    seri2_slice,
+};
+
+/// Which worker runs which part.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Assignment {
+    /// Part `i` always runs on worker `i`, so the data a worker touched
+    /// in one call is still in its cache in the next.
+    Pinned,
+    /// Workers claim the next part as soon as they are free, which
+    /// evens out workers that wake up late or parts that run long.
+    Dynamic,
+}
+
+/// A pool of workers that runs one `seri2_slice` part per worker.
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::pool::PartsPool;
+///
+/// let mut pool = PartsPool::new(4);
+/// let mut v = vec![1u64; 100_000];
+/// for _ in 0..10 {
+///     pool.for_each_part_mut(&mut v, |_, part| part.iter_mut().for_each(|x| *x *= 2));
+/// }
+/// let sums = pool.map_parts(&v, |_, part| part.iter().sum::<u64>());
+/// assert_eq!(sums.iter().sum::<u64>(), 100_000 * 1024);
+/// ```
+pub struct PartsPool {
+    shared: Arc<Shared>,
+    threads: Vec<JoinHandle<()>>,
+    assignment: Assignment,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    /// Signalled when a job is posted or the pool shuts down.
+    start: Condvar,
+    /// Signalled when the last worker finishes a job.
+    done: Condvar,
+}
+
+struct State {
+    /// Bumped for every job, so workers can tell a new one from the
+    /// one they just finished.
+    generation: u64,
+    job: Option<JobRef>,
+    /// Background workers still running the current job.
+    running: usize,
+    shutdown: bool,
+}
+
+/// A job with its lifetime erased. It is only called between posting
+/// it and `running` dropping back to 0, while the caller keeps it
+/// alive.
+#[derive(Clone, Copy)]
+struct JobRef(*const (dyn Fn(usize) + Sync + 'static));
+
+// SAFETY: The job is `Sync`, and outlives every use of the pointer.
+unsafe impl Send for JobRef {}
+
+impl PartsPool {
+    /// Creates a pool of `n_workers` workers, counting the calling
+    /// thread, with [`Assignment::Pinned`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n_workers` is 0, or if spawning a thread fails.
+    #[inline]
+    pub fn new(n_workers: usize) -> Self {
+        Self::with_assignment(n_workers, Assignment::Pinned)
+    }
+
+    /// Creates a pool of `n_workers` workers, counting the calling
+    /// thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n_workers` is 0, or if spawning a thread fails.
+    pub fn with_assignment(n_workers: usize, assignment: Assignment) -> Self {
+        assert!(n_workers > 0, "a pool needs at least one worker");
+
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                generation: 0,
+                job: None,
+                running: 0,
+                shutdown: false,
+            }),
+            start: Condvar::new(),
+            done: Condvar::new(),
+        });
+
+        let threads = (1..n_workers)
+            .map(|worker| {
+                let shared = Arc::clone(&shared);
+                thread::Builder::new()
+                    .name(format!("parts-pool-{worker}"))
+                    .spawn(move || shared.work(worker))
+                    .expect("failed to spawn thread")
+            })
+            .collect();
+
+        Self {
+            shared,
+            threads,
+            assignment,
+        }
+    }
+
+    /// The number of workers, counting the calling thread. Slices are
+    /// split into a maximum of this many parts.
+    #[inline]
+    pub fn n_workers(&self) -> usize {
+        self.threads.len() + 1
+    }
+
+    #[inline]
+    pub fn assignment(&self) -> Assignment {
+        self.assignment
+    }
+
+    /// Calls `f(index, part)` on every part of `slice`, one part per
+    /// worker, and returns once all of them are done.
+    ///
+    /// # Panics
+    ///
+    /// Panics with a [`PartPanic`] if `f` panics.
+    pub fn for_each_part<T, F>(&mut self, slice: &[T], f: F)
+    where
+        T: Sync,
+        F: Fn(usize, &[T]) + Sync,
+    {
+        let parts = seri2_slice::Parts::new(slice, self.n_workers());
+        self.run(parts, &f);
+    }
+
+    /// Like [`for_each_part`](Self::for_each_part), but with mutable
+    /// parts.
+    pub fn for_each_part_mut<T, F>(&mut self, slice: &mut [T], f: F)
+    where
+        T: Send,
+        F: Fn(usize, &mut [T]) + Sync,
+    {
+        let parts = parts_mut(slice, self.n_workers());
+        self.run(parts, &f);
+    }
+
+    /// Calls `f(index, part)` on every part of `slice`, one part per
+    /// worker, and returns the results in part order.
+    ///
+    /// # Panics
+    ///
+    /// Panics with a [`PartPanic`] if `f` panics.
+    pub fn map_parts<T, R, F>(&mut self, slice: &[T], f: F) -> Vec<R>
+    where
+        T: Sync,
+        R: Send,
+        F: Fn(usize, &[T]) -> R + Sync,
+    {
+        let parts = seri2_slice::Parts::new(slice, self.n_workers());
+        self.run(parts, &f)
+    }
+
+    /// Like [`map_parts`](Self::map_parts), but with mutable parts.
+    pub fn map_parts_mut<T, R, F>(&mut self, slice: &mut [T], f: F) -> Vec<R>
+    where
+        T: Send,
+        R: Send,
+        F: Fn(usize, &mut [T]) -> R + Sync,
+    {
+        let parts = parts_mut(slice, self.n_workers());
+        self.run(parts, &f)
+    }
+
+    /// Calls `f(index, part, barrier)` on every part of `slice`, one
+    /// part per worker, and returns once all of them are done.
+    ///
+    /// Unlike a call per phase, the parts can sync up between phases by
+    /// calling [`barrier.wait()`](PoolBarrier::wait), without waking the
+    /// workers again. A part that returns early stops counting towards
+    /// the barrier.
+    ///
+    /// # Panics
+    ///
+    /// Panics with a [`PartPanic`] if `f` panics. The parts waiting at
+    /// the barrier at that point are woken up and unwind as well, but
+    /// the panic reported is the one that broke the barrier.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    ///
+    /// use sheps_chunkerino::pool::PartsPool;
+    ///
+    /// let mut pool = PartsPool::new(4);
+    /// let v: Vec<u64> = (1..=100).collect();
+    /// let sums: Vec<_> = (0..4).map(|_| AtomicU64::new(0)).collect();
+    /// let offsets: Vec<_> = (0..4).map(|_| AtomicU64::new(0)).collect();
+    ///
+    /// pool.for_each_part_phased(&v, |i, part, barrier| {
+    ///     sums[i].store(part.iter().sum(), Ordering::Relaxed);
+    ///     barrier.wait();
+    ///     // Every part's sum is in by now.
+    ///     let before = sums[..i].iter().map(|s| s.load(Ordering::Relaxed)).sum();
+    ///     offsets[i].store(before, Ordering::Relaxed);
+    /// });
+    ///
+    /// let offsets: Vec<_> = offsets.iter().map(|o| o.load(Ordering::Relaxed)).collect();
+    /// assert_eq!(offsets, [0, 325, 1275, 2850]);
+    /// ```
+    pub fn for_each_part_phased<T, F>(&mut self, slice: &[T], f: F)
+    where
+        T: Sync,
+        F: Fn(usize, &[T], &PoolBarrier) + Sync,
+    {
+        let parts = seri2_slice::Parts::new(slice, self.n_workers());
+        let barrier = PoolBarrier::new(parts.len());
+
+        let results = self.run(parts, &|i, part| {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| f(i, part, &barrier)));
+            match result {
+                Ok(()) => barrier.leave(),
+                Err(_) => barrier.break_wait(),
+            }
+            result
+        });
+
+        // Skip the parts that only unwound because the barrier broke.
+        let panicked = results
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, result)| Some((i, result.err()?)))
+            .find(|(_, payload)| !payload.is::<BarrierBroken>());
+        if let Some((index, payload)) = panicked {
+            panic::resume_unwind(Box::new(PartPanic::new(index, payload)));
+        }
+    }
+
+    fn run<P, R, F>(&mut self, parts: impl Iterator<Item = P>, f: &F) -> Vec<R>
+    where
+        P: Send,
+        R: Send,
+        F: Fn(usize, P) -> R + Sync,
+    {
+        let parts: Vec<_> = parts.map(Slot::new).collect();
+        let results: Vec<Slot<thread::Result<R>>> = parts.iter().map(|_| Slot::empty()).collect();
+        let next = AtomicUsize::new(0);
+
+        let run_part = |i: usize| {
+            // SAFETY: Every part is claimed by exactly one worker, which
+            // is the only one to touch its slots during the job.
+            let part = unsafe { parts[i].take() };
+            let result = panic::catch_unwind(AssertUnwindSafe(|| f(i, part)));
+            unsafe { results[i].put(result) };
+        };
+        let job = |worker: usize| match self.assignment {
+            Assignment::Pinned => {
+                if worker < parts.len() {
+                    run_part(worker);
+                }
+            }
+            Assignment::Dynamic => loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= parts.len() {
+                    break;
+                }
+                run_part(i);
+            },
+        };
+
+        self.shared.execute(self.threads.len(), &job);
+
+        // SAFETY: There are no more parts than workers, so every part
+        // was claimed and stored its result before the job was done.
+        let results: Vec<_> = results
+            .into_iter()
+            .map(|result| unsafe { result.into_inner() })
+            .collect();
+
+        let mut out = Vec::with_capacity(results.len());
+        for (index, result) in results.into_iter().enumerate() {
+            match result {
+                Ok(r) => out.push(r),
+                Err(payload) => panic::resume_unwind(Box::new(PartPanic::new(index, payload))),
+            }
+        }
+        out
+    }
+}
+
+impl Drop for PartsPool {
+    fn drop(&mut self) {
+        lock(&self.shared.state).shutdown = true;
+        self.shared.start.notify_all();
+
+        for thread in self.threads.drain(..) {
+            // Jobs catch their own panics, so workers exit cleanly.
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Shared {
+    /// Runs `job` on this thread as worker 0 and on all `n_threads`
+    /// background workers, and waits for all of them.
+    fn execute(&self, n_threads: usize, job: &(dyn Fn(usize) + Sync)) {
+        // SAFETY: Only the lifetime changes. The workers are done with
+        // the job before this function returns, and the guard below
+        // aborts rather than let a panic leave early.
+        let job = unsafe {
+            mem::transmute::<
+                *const (dyn Fn(usize) + Sync + '_),
+                *const (dyn Fn(usize) + Sync + 'static),
+            >(job)
+        };
+
+        // A panic between posting the job and the workers finishing it
+        // would leave them holding a dangling reference.
+        let guard = AbortOnUnwind;
+        {
+            let mut state = lock(&self.state);
+            state.job = Some(JobRef(job));
+            state.running = n_threads;
+            state.generation += 1;
+        }
+        self.start.notify_all();
+
+        // SAFETY: `job` is still borrowed from the caller.
+        unsafe { (*job)(0) };
+
+        let mut state = lock(&self.state);
+        while state.running > 0 {
+            state = self.done.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        state.job = None;
+        mem::forget(guard);
+    }
+
+    /// The loop of a background worker.
+    fn work(&self, worker: usize) {
+        let mut seen = 0;
+
+        loop {
+            let job = {
+                let mut state = lock(&self.state);
+                while state.generation == seen && !state.shutdown {
+                    state = self.start.wait(state).unwrap_or_else(|e| e.into_inner());
+                }
+                if state.shutdown {
+                    return;
+                }
+                seen = state.generation;
+                state.job.expect("a job was posted")
+            };
+
+            // If the job unwound here, `running` would never drop to 0
+            // and the caller would wait forever.
+            let guard = AbortOnUnwind;
+            // SAFETY: The job stays alive until `running` drops to 0,
+            // which cannot happen before this worker is done with it.
+            unsafe { (*job.0)(worker) };
+            mem::forget(guard);
+
+            let mut state = lock(&self.state);
+            state.running -= 1;
+            if state.running == 0 {
+                self.done.notify_all();
+            }
+        }
+    }
+}
+
+/// Lets the parts of [`PartsPool::for_each_part_phased`] wait for each
+/// other.
+pub struct PoolBarrier {
+    state: Mutex<BarrierState>,
+    /// Signalled when the last part arrives or the barrier breaks.
+    released: Condvar,
+}
+
+struct BarrierState {
+    /// Parts that have not returned yet.
+    parties: usize,
+    /// Parts waiting for the others.
+    waiting: usize,
+    /// Bumped every time the waiting parts are released.
+    generation: u64,
+    /// Set once a part panics. Waiting would never end after that.
+    broken: bool,
+}
+
+/// What parts waiting at a broken barrier unwind with.
+struct BarrierBroken;
+
+impl PoolBarrier {
+    fn new(parties: usize) -> Self {
+        Self {
+            state: Mutex::new(BarrierState {
+                parties,
+                waiting: 0,
+                generation: 0,
+                broken: false,
+            }),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Blocks until every part that has not returned yet calls `wait`.
+    ///
+    /// # Panics
+    ///
+    /// Unwinds if another part panics, since the wait would never end.
+    /// [`PartsPool::for_each_part_phased`] reports the original panic
+    /// instead.
+    pub fn wait(&self) {
+        let mut state = lock(&self.state);
+        if !state.broken {
+            state.waiting += 1;
+            if state.waiting == state.parties {
+                state.release();
+                self.released.notify_all();
+                return;
+            }
+
+            let generation = state.generation;
+            while state.generation == generation && !state.broken {
+                state = self.released.wait(state).unwrap_or_else(|e| e.into_inner());
+            }
+        }
+
+        if state.broken {
+            drop(state);
+            panic::resume_unwind(Box::new(BarrierBroken));
+        }
+    }
+
+    /// Stops counting a part that returned.
+    fn leave(&self) {
+        let mut state = lock(&self.state);
+        state.parties -= 1;
+        if state.waiting > 0 && state.waiting == state.parties {
+            state.release();
+            self.released.notify_all();
+        }
+    }
+
+    /// Wakes up every waiting part, to unwind.
+    fn break_wait(&self) {
+        lock(&self.state).broken = true;
+        self.released.notify_all();
+    }
+}
+
+impl BarrierState {
+    fn release(&mut self) {
+        self.waiting = 0;
+        self.generation += 1;
+    }
+}
+
+/// A value handed to or from a worker during one job.
+///
+/// Unlike a `Mutex<Option<T>>`, a slot costs nothing to access: the
+/// callers make sure that only one thread touches it at a time, with
+/// the job's start and end ordering the accesses.
+struct Slot<T>(UnsafeCell<MaybeUninit<T>>);
+
+// SAFETY: Accesses are never concurrent, see `Slot`.
+unsafe impl<T: Send> Sync for Slot<T> {}
+
+impl<T> Slot<T> {
+    #[inline]
+    fn new(value: T) -> Self {
+        Self(UnsafeCell::new(MaybeUninit::new(value)))
+    }
+
+    #[inline]
+    fn empty() -> Self {
+        Self(UnsafeCell::new(MaybeUninit::uninit()))
+    }
+
+    /// # Safety
+    ///
+    /// The slot has to hold a value, which is then moved out, and no
+    /// other thread may access it at the same time.
+    #[inline]
+    unsafe fn take(&self) -> T {
+        (*self.0.get()).assume_init_read()
+    }
+
+    /// # Safety
+    ///
+    /// No other thread may access the slot at the same time. A value
+    /// already in it is leaked.
+    #[inline]
+    unsafe fn put(&self, value: T) {
+        (*self.0.get()).write(value);
+    }
+
+    /// # Safety
+    ///
+    /// The slot has to hold a value.
+    #[inline]
+    unsafe fn into_inner(self) -> T {
+        self.0.into_inner().assume_init()
+    }
+}
+
+/// Aborts the process when dropped. Forgotten once the code it guards
+/// has finished without unwinding.
+struct AbortOnUnwind;
+
+impl Drop for AbortOnUnwind {
+    fn drop(&mut self) {
+        process::abort();
+    }
+}
+
+/// Locks `mutex`, ignoring poisoning: every critical section here
+/// leaves the data consistent, and part panics are caught outside them.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{thread::ThreadId, vec};
+
+    #[test]
+    fn matches_scoped_threads() {
+        for n_workers in 1..=6 {
+            for assignment in [Assignment::Pinned, Assignment::Dynamic] {
+                let mut pool = PartsPool::with_assignment(n_workers, assignment);
+                assert_eq!(pool.n_workers(), n_workers);
+
+                for len in 0..30 {
+                    let items: Vec<_> = (0..len).collect();
+                    let expected = crate::par::map_parts(&items, n_workers, |i, p| (i, p.to_vec()));
+                    let parts = pool.map_parts(&items, |i, p| (i, p.to_vec()));
+                    assert_eq!(parts, expected, "{assignment:?}, n={n_workers}, len={len}");
+
+                    let mut copy = items.clone();
+                    pool.for_each_part_mut(&mut copy, |i, part| part.fill(i));
+                    let owners: Vec<_> = expected
+                        .iter()
+                        .flat_map(|(i, p)| vec![*i; p.len()])
+                        .collect();
+                    assert_eq!(copy, owners, "{assignment:?}, n={n_workers}, len={len}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pinned_parts_stay_on_their_worker() {
+        let mut pool = PartsPool::new(4);
+        let data = [0u8; 100];
+
+        let first: Vec<ThreadId> = pool.map_parts(&data, |_, _| thread::current().id());
+        assert_eq!(first[0], thread::current().id());
+        for _ in 0..20 {
+            let again: Vec<_> = pool.map_parts(&data, |_, _| thread::current().id());
+            assert_eq!(again, first);
+        }
+
+        let mut names = pool.map_parts(&data, |_, _| thread::current().name().map(String::from));
+        names.remove(0);
+        assert_eq!(
+            names,
+            ["parts-pool-1", "parts-pool-2", "parts-pool-3"].map(|s| Some(s.into()))
+        );
+    }
+
+    #[test]
+    fn survives_panics() {
+        let mut pool = PartsPool::new(3);
+
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool.for_each_part(&[0; 6], |i, _| assert_ne!(i, 1, "part {i} is broken"));
+        }))
+        .unwrap_err();
+        let part_panic = payload.downcast::<PartPanic>().unwrap();
+        assert_eq!(part_panic.index(), 1);
+
+        // The worker that panicked is still there.
+        let sums = pool.map_parts(&[1; 6], |_, p| p.iter().sum::<i32>());
+        assert_eq!(sums, [2, 2, 2]);
+    }
+
+    #[test]
+    fn phases_see_each_other() {
+        for n_workers in 1..=5 {
+            for assignment in [Assignment::Pinned, Assignment::Dynamic] {
+                let mut pool = PartsPool::with_assignment(n_workers, assignment);
+
+                for len in [0, 1, 3, 20] {
+                    let ctx = format!("{assignment:?}, n={n_workers}, len={len}");
+                    let items = vec![1; len];
+                    let n_parts = crate::layout::PartsLayout::new(len, n_workers).n_parts();
+                    let arrived = AtomicUsize::new(0);
+
+                    pool.for_each_part_phased(&items, |_, _, barrier| {
+                        for phase in 0..10 {
+                            arrived.fetch_add(1, Ordering::Relaxed);
+                            barrier.wait();
+                            // Everyone arrived, and nobody goes on before
+                            // everyone has looked.
+                            let seen = arrived.load(Ordering::Relaxed);
+                            assert_eq!(seen, (phase + 1) * n_parts, "{ctx}");
+                            barrier.wait();
+                        }
+                    });
+                    assert_eq!(arrived.into_inner(), 10 * n_parts, "{ctx}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parts_can_leave_early() {
+        let mut pool = PartsPool::new(4);
+        let waits = AtomicUsize::new(0);
+
+        pool.for_each_part_phased(&[0; 4], |i, _, barrier| {
+            for _ in 0..i {
+                barrier.wait();
+                waits.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+        assert_eq!(waits.into_inner(), 1 + 2 + 3);
+    }
+
+    #[test]
+    fn panics_break_the_barrier() {
+        let mut pool = PartsPool::with_assignment(4, Assignment::Dynamic);
+
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool.for_each_part_phased(&[0; 8], |i, _, barrier| {
+                barrier.wait();
+                if i == 2 {
+                    panic!("part 2 is broken");
+                }
+                barrier.wait();
+            })
+        }))
+        .unwrap_err();
+
+        let part_panic = payload.downcast::<PartPanic>().unwrap();
+        assert_eq!(part_panic.index(), 2);
+        assert_eq!(part_panic.message(), Some("part 2 is broken"));
+
+        // The workers that were woken up are still there.
+        let sums = pool.map_parts(&[1; 8], |_, p| p.iter().sum::<i32>());
+        assert_eq!(sums, [2, 2, 2, 2]);
+    }
+
+    #[test]
+    fn drops_results_on_panic() {
+        #[derive(Debug)]
+        struct Counted<'a>(&'a AtomicUsize);
+
+        impl Drop for Counted<'_> {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let drops = AtomicUsize::new(0);
+        let mut pool = PartsPool::with_assignment(4, Assignment::Dynamic);
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool.map_parts(&[0; 8], |i, _| {
+                assert_ne!(i, 2, "part 2 is broken");
+                Counted(&drops)
+            })
+        }))
+        .unwrap_err();
+
+        assert_eq!(payload.downcast::<PartPanic>().unwrap().index(), 2);
+        assert_eq!(drops.into_inner(), 3);
+    }
+}
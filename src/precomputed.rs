@@ -1,3 +1,8 @@
+use crate::{
+    layout::PartsLayout,
+    split::{is_zst, zst_slice},
+};
+
 super::ext!();
 
 pub struct Parts<'a, T> {
@@ -17,18 +22,14 @@ impl<'a, T> Parts<'a, T> {
         // let n_head = remainder;
         // let n_tail = n_parts - n_head;
 
-        let len = slice.len();
-        let tail_len = len / n_parts;
-        let head_len = tail_len + 1;
-        let n_head = len - (tail_len * n_parts);
-        let n_tail = n_parts - n_head;
+        let layout = PartsLayout::new(slice.len(), n_parts);
 
         Self {
             slice,
-            n_head,
-            n_tail,
-            head_len,
-            tail_len,
+            n_head: layout.n_big(),
+            n_tail: layout.n_small(),
+            head_len: layout.big_len(),
+            tail_len: layout.small_len(),
         }
     }
 }
@@ -45,18 +46,31 @@ impl<'a, T> Iterator for Parts<'a, T> {
             tail_len,
         } = *self;
 
-        if n_head > 0 {
-            let (next, remaining) = slice.split_at(head_len);
-            self.slice = remaining;
+        let len = if n_head > 0 {
             self.n_head -= 1;
-            Some(next)
+            head_len
         } else if n_tail > 0 {
-            let (next, remaining) = slice.split_at(tail_len);
-            self.slice = remaining;
             self.n_tail -= 1;
-            Some(next)
+            tail_len
         } else {
-            None
+            return None;
+        };
+
+        if is_zst::<T>() {
+            // No need to split, only the lengths matter.
+            self.slice = zst_slice(slice.len() - len);
+            return Some(zst_slice(len));
         }
+
+        let (next, remaining) = slice.split_at(len);
+        self.slice = remaining;
+        Some(next)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    crate::conformance::conformance_tests!();
+}
@@ -0,0 +1,206 @@
+//! Parts of a sorted slice that each cover a range of keys, for writing
+//! range-partitioned output.
+//!
+//! The splitter keys between the parts can be kept around to route
+//! other, unsorted data into the same buckets.
+
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+use crate::by_key::PartsByKeyExt;
+
+pub trait QuantilePartsExt<T> {
+    /// Divides the sorted slice into a maximum of `n_parts` parts of
+    /// roughly equal length, none of which share a key.
+    ///
+    /// The parts are those of
+    /// [`parts_by_key`](PartsByKeyExt::parts_by_key), with the empty
+    /// ones left out. The slice must be sorted by `key_fn`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n_parts` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sheps_chunkerino::quantile::QuantilePartsExt;
+    ///
+    /// let slice = [1, 1, 2, 2, 2, 2, 3, 4];
+    /// let quantiles = slice.quantile_parts(3, |&x| x);
+    ///
+    /// let parts: Vec<_> = quantiles.parts().iter().map(|p| p.slice).collect();
+    /// assert_eq!(parts, [&[1, 1][..], &[2, 2, 2, 2], &[3, 4]]);
+    /// assert_eq!(quantiles.parts()[2].keys, 3..=4);
+    ///
+    /// let splitters = quantiles.splitters();
+    /// assert_eq!(splitters.keys(), [2, 3]);
+    /// assert_eq!(splitters.bucket_of(&0), 0);
+    /// assert_eq!(splitters.bucket_of(&2), 1);
+    /// assert_eq!(splitters.bucket_of(&9), 2);
+    /// ```
+    fn quantile_parts<K, F>(&self, n_parts: usize, key_fn: F) -> Quantiles<'_, T, K>
+    where
+        K: Ord,
+        F: Fn(&T) -> K;
+}
+
+impl<T> QuantilePartsExt<T> for [T] {
+    fn quantile_parts<K, F>(&self, n_parts: usize, key_fn: F) -> Quantiles<'_, T, K>
+    where
+        K: Ord,
+        F: Fn(&T) -> K,
+    {
+        let parts: Vec<_> = self
+            .parts_by_key(n_parts, &key_fn)
+            .filter_map(|slice| {
+                let keys = key_fn(slice.first()?)..=key_fn(slice.last()?);
+                Some(QuantilePart { slice, keys })
+            })
+            .collect();
+
+        let keys = parts
+            .iter()
+            .skip(1)
+            .map(|part| key_fn(&part.slice[0]))
+            .collect();
+
+        Quantiles {
+            parts,
+            splitters: Splitters { keys },
+        }
+    }
+}
+
+/// The result of [`QuantilePartsExt::quantile_parts`].
+#[derive(Clone, Debug)]
+pub struct Quantiles<'a, T, K> {
+    parts: Vec<QuantilePart<'a, T, K>>,
+    splitters: Splitters<K>,
+}
+
+impl<'a, T, K> Quantiles<'a, T, K> {
+    /// The parts, in order. None of them are empty.
+    #[inline]
+    pub fn parts(&self) -> &[QuantilePart<'a, T, K>] {
+        &self.parts
+    }
+
+    /// The keys separating the parts.
+    #[inline]
+    pub fn splitters(&self) -> &Splitters<K> {
+        &self.splitters
+    }
+
+    /// Splits off the splitters, to keep them after the slice is gone.
+    #[inline]
+    pub fn into_parts(self) -> (Vec<QuantilePart<'a, T, K>>, Splitters<K>) {
+        (self.parts, self.splitters)
+    }
+}
+
+/// A non-empty part of a sorted slice, with the keys of its first and
+/// last element.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuantilePart<'a, T, K> {
+    pub slice: &'a [T],
+    pub keys: RangeInclusive<K>,
+}
+
+/// Keys separating buckets: bucket `i` holds the keys from
+/// `keys()[i - 1]` up to, but not including, `keys()[i]`.
+///
+/// The first bucket has no lower bound and the last has no upper bound.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Splitters<K> {
+    /// Strictly increasing.
+    keys: Vec<K>,
+}
+
+impl<K: Ord> Splitters<K> {
+    /// The bucket `key` belongs in.
+    ///
+    /// For keys that occur in the partitioned slice, this is the index
+    /// of the part containing them.
+    #[inline]
+    pub fn bucket_of(&self, key: &K) -> usize {
+        self.keys.partition_point(|splitter| splitter <= key)
+    }
+}
+
+impl<K> Splitters<K> {
+    /// The first key of every bucket but the first.
+    #[inline]
+    pub fn keys(&self) -> &[K] {
+        &self.keys
+    }
+
+    /// The number of buckets, one more than the number of keys.
+    #[inline]
+    pub fn n_buckets(&self) -> usize {
+        self.keys.len() + 1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conformance;
+    use std::vec::Vec;
+
+    #[test]
+    fn buckets_match_parts() {
+        for (items, n) in conformance::cases() {
+            let ctx = format!("len={}, n={n}", items.len());
+            let keys: Vec<_> = items.iter().map(|&i| (i * 8).isqrt()).collect();
+            let quantiles = keys.quantile_parts(n, |&k| k);
+            let (parts, splitters) = quantiles.clone().into_parts();
+
+            let slices: Vec<_> = parts.iter().map(|p| p.slice).collect();
+            assert_eq!(slices.concat(), keys, "{ctx}");
+            assert!(parts.len() <= n, "{ctx}");
+            assert_eq!(splitters.n_buckets(), parts.len().max(1), "{ctx}");
+            assert!(splitters.keys().windows(2).all(|w| w[0] < w[1]), "{ctx}");
+
+            for (i, part) in parts.iter().enumerate() {
+                assert!(!part.slice.is_empty(), "{ctx}");
+                assert_eq!(part.keys.start(), &part.slice[0], "{ctx}");
+                assert_eq!(part.keys.end(), part.slice.last().unwrap(), "{ctx}");
+                for key in part.slice {
+                    assert_eq!(splitters.bucket_of(key), i, "{ctx}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn routes_unseen_keys() {
+        let sorted = [10, 10, 20, 30, 30, 30, 40, 50];
+        let quantiles = sorted.quantile_parts(4, |&x| x);
+        let splitters = quantiles.splitters();
+        assert_eq!(splitters.keys(), [20, 30, 40]);
+
+        let other = [5, 10, 15, 20, 25, 35, 45, 99];
+        let buckets: Vec<_> = other.iter().map(|k| splitters.bucket_of(k)).collect();
+        assert_eq!(buckets, [0, 0, 0, 1, 1, 2, 3, 3]);
+    }
+
+    #[test]
+    fn single_key() {
+        let sorted = [7; 10];
+        let quantiles = sorted.quantile_parts(4, |&x| x);
+        assert_eq!(
+            quantiles.parts(),
+            [QuantilePart {
+                slice: &sorted[..],
+                keys: 7..=7
+            }]
+        );
+        assert_eq!(quantiles.splitters().n_buckets(), 1);
+        assert_eq!(quantiles.splitters().bucket_of(&0), 0);
+
+        let empty = [0u8; 0].quantile_parts(4, |&x| x);
+        assert!(empty.parts().is_empty());
+        assert_eq!(empty.splitters().n_buckets(), 1);
+    }
+}
@@ -0,0 +1,288 @@
+//! Moving from one partitioning of a slice to another when the number
+//! of parts changes, for example when a worker pool grows or shrinks.
+//!
+//! Part `i` of the old layout and part `i` of the new one are assumed
+//! to live in the same place, so only elements whose part index changes
+//! have to move.
+
+use core::ops::Range;
+
+use crate::layout::{BoundaryLayout, PartsLayout};
+
+/// How to pick the new layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// The balanced layout `seri2_slice::Parts` uses.
+    Balanced,
+    /// Keep an old boundary wherever it is within `slack` elements of
+    /// the balanced one, so nothing crosses it.
+    ///
+    /// Each part ends up at most `2 * slack` elements longer or shorter
+    /// than in the balanced layout. `slack` is capped below the
+    /// shortest balanced part length, so no part ends up empty.
+    Sticky { slack: usize },
+}
+
+/// Repartitions the elements of `old` into a maximum of `new_n` balanced
+/// parts.
+///
+/// # Panics
+///
+/// Panics if `new_n` is 0.
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::{layout::PartsLayout, repartition::{repartition, Move}};
+///
+/// let plan = repartition(PartsLayout::new(12, 2), 3);
+/// assert_eq!(plan.new_layout().boundaries(), [0, 4, 8, 12]);
+///
+/// let moves: Vec<_> = plan.moves().collect();
+/// assert_eq!(
+///     moves,
+///     [
+///         Move { from: 0, to: 1, range: 4..6 },
+///         Move { from: 1, to: 2, range: 8..12 },
+///     ]
+/// );
+/// ```
+pub fn repartition(old: PartsLayout, new_n: usize) -> Repartition {
+    repartition_with(old, new_n, Mode::Balanced)
+}
+
+/// Repartitions the elements of `old` into a maximum of `new_n` parts,
+/// picking the new layout according to `mode`.
+///
+/// # Panics
+///
+/// Panics if `new_n` is 0.
+pub fn repartition_with(old: PartsLayout, new_n: usize, mode: Mode) -> Repartition {
+    let balanced = PartsLayout::new(old.len(), new_n);
+
+    let new = match mode {
+        Mode::Balanced => BoundaryLayout::from(balanced),
+        Mode::Sticky { slack } => {
+            let shortest = if balanced.n_small() > 0 {
+                balanced.small_len()
+            } else {
+                balanced.big_len()
+            };
+            let slack = slack.min(shortest.saturating_sub(1));
+
+            let mut old_boundaries = old.boundaries();
+            let last = balanced.n_parts();
+            let boundaries = balanced
+                .boundaries()
+                .enumerate()
+                .map(|(k, b)| match old_boundaries.next() {
+                    // The first and last boundaries are 0 and `len` either
+                    // way, but the old `len` may line up with another one.
+                    Some(old_b) if k < last && old_b.abs_diff(b) <= slack => old_b,
+                    _ => b,
+                })
+                .collect();
+
+            BoundaryLayout::new(boundaries).expect("sticky boundaries are increasing")
+        }
+    };
+
+    Repartition { old, new }
+}
+
+/// The result of [`repartition`]: the new layout and what has to move
+/// to get there.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Repartition {
+    old: PartsLayout,
+    new: BoundaryLayout,
+}
+
+impl Repartition {
+    /// The layout the parts were repartitioned from.
+    #[inline]
+    pub fn old_layout(&self) -> PartsLayout {
+        self.old
+    }
+
+    /// The layout the parts were repartitioned into.
+    #[inline]
+    pub fn new_layout(&self) -> &BoundaryLayout {
+        &self.new
+    }
+
+    /// The ranges of elements that change parts, in order.
+    #[inline]
+    pub fn moves(&self) -> Moves<'_> {
+        Moves {
+            old: self.old,
+            new: self.new.boundaries(),
+            pos: 0,
+            old_part: 0,
+            new_part: 0,
+        }
+    }
+
+    /// The total number of elements that change parts.
+    pub fn moved_len(&self) -> usize {
+        self.moves().map(|m| m.range.len()).sum()
+    }
+}
+
+/// Elements in `range` move from old part `from` to new part `to`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub from: usize,
+    pub to: usize,
+    pub range: Range<usize>,
+}
+
+/// Iterator over the [`Move`]s of a [`Repartition`].
+pub struct Moves<'a> {
+    old: PartsLayout,
+    new: &'a [usize],
+    pos: usize,
+    old_part: usize,
+    new_part: usize,
+}
+
+impl Iterator for Moves<'_> {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Walk both layouts' boundaries at once. Between two consecutive
+        // boundaries of either, the old and new part stay the same.
+        while self.pos < self.old.len() {
+            let old_end = self.old.part_range(self.old_part).end;
+            let new_end = self.new[self.new_part + 1];
+            let end = old_end.min(new_end);
+
+            let (from, to, range) = (self.old_part, self.new_part, self.pos..end);
+
+            self.pos = end;
+            if old_end == end {
+                self.old_part += 1;
+            }
+            if new_end == end {
+                self.new_part += 1;
+            }
+
+            if from != to && !range.is_empty() {
+                return Some(Move { from, to, range });
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::vec;
+
+    /// Checks that applying `plan`'s moves to the old owners gives the
+    /// new owners.
+    fn check_moves(plan: &Repartition) {
+        let old = plan.old_layout();
+        let new = plan.new_layout();
+
+        let mut owners: Vec<_> = (0..old.len()).map(|i| old.part_of(i)).collect();
+        for m in plan.moves() {
+            for owner in &mut owners[m.range] {
+                assert_eq!(*owner, m.from);
+                *owner = m.to;
+            }
+        }
+
+        for (i, owner) in owners.into_iter().enumerate() {
+            assert_eq!(owner, new.part_of(i), "element {i}");
+        }
+    }
+
+    #[test]
+    fn moves_reach_the_new_layout() {
+        for len in 0..=40 {
+            for old_n in 1..=len + 2 {
+                for new_n in 1..=len + 2 {
+                    let old = PartsLayout::new(len, old_n);
+                    let ctx = format!("len={len}, {old_n} -> {new_n}");
+
+                    let plan = repartition(old, new_n);
+                    let balanced = PartsLayout::new(len, new_n);
+                    assert_eq!(*plan.new_layout(), BoundaryLayout::from(balanced), "{ctx}");
+                    check_moves(&plan);
+
+                    for slack in [0, 1, 3, usize::MAX] {
+                        let plan = repartition_with(old, new_n, Mode::Sticky { slack });
+                        let new = plan.new_layout();
+                        assert_eq!(new.n_parts(), balanced.n_parts(), "{ctx}");
+                        for i in 0..new.n_parts() {
+                            assert!(!new.part_range(i).is_empty(), "{ctx}: part {i} is empty");
+                        }
+                        check_moves(&plan);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn same_n_moves_nothing() {
+        let old = PartsLayout::new(1000, 8);
+        assert_eq!(repartition(old, 8).moves().count(), 0);
+        assert_eq!(
+            repartition_with(old, 8, Mode::Sticky { slack: 10 })
+                .moves()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn sticky_trades_balance_for_movement() {
+        // 1003 over 8 parts: boundaries at 126, 252, 378, ...
+        // 1003 over 9 parts: boundaries at 112, 224, 336, ...
+        let old = PartsLayout::new(1003, 8);
+        let balanced = repartition(old, 9);
+        let sticky = repartition_with(old, 9, Mode::Sticky { slack: 16 });
+
+        assert!(sticky.moved_len() < balanced.moved_len());
+
+        let new = sticky.new_layout();
+        assert_eq!(new.boundaries()[1], 126);
+        for i in 0..new.n_parts() {
+            let len = new.part_range(i).len();
+            assert!((111 - 32..=112 + 32).contains(&len), "part {i}: {len}");
+        }
+
+        assert_eq!(
+            repartition_with(old, 9, Mode::Sticky { slack: 0 }),
+            balanced
+        );
+    }
+
+    #[test]
+    fn growing_pool() {
+        let plan = repartition(PartsLayout::new(80, 8), 10);
+        let moves: Vec<_> = plan.moves().collect();
+
+        // Part 0 keeps 0..8, part 1 gets 8..10 from part 0, ...
+        assert_eq!(
+            moves[..2],
+            vec![
+                Move {
+                    from: 0,
+                    to: 1,
+                    range: 8..10
+                },
+                Move {
+                    from: 1,
+                    to: 2,
+                    range: 16..20
+                },
+            ]
+        );
+        assert_eq!(plan.moved_len(), 60);
+    }
+}
@@ -0,0 +1,384 @@
+//! Loop schedules in the style of OpenMP's `schedule` clause, for
+//! handing out the indices `0..len` to a fixed number of workers.
+//!
+//! A [`Claimer`] holds the shared state of one loop. Each worker pulls
+//! its ranges from [`Claimer::worker`], and every index is handed out
+//! exactly once no matter how the workers interleave.
+
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::layout::PartsLayout;
+
+/// How the indices of a loop are split between workers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Schedule {
+    /// One balanced part per worker, laid out like `seri2_slice::Parts`.
+    Static,
+    /// Chunks of the given size, dealt out to the workers round-robin.
+    StaticChunked(usize),
+    /// Chunks of the given size, claimed by whichever worker asks next.
+    Dynamic(usize),
+    /// Chunks of the remaining length divided by the number of
+    /// workers, but at least the given size, claimed by whichever
+    /// worker asks next. Chunks shrink as the loop goes on, so early
+    /// claims are cheap and late ones even out the load.
+    Guided(usize),
+}
+
+impl Schedule {
+    /// Starts handing out `0..len` to `n_workers` workers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n_workers` or the chunk size is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sheps_chunkerino::schedule::Schedule;
+    ///
+    /// let claimer = Schedule::StaticChunked(2).claimer(9, 2);
+    /// let ranges: Vec<_> = claimer.worker(1).collect();
+    /// assert_eq!(ranges, [2..4, 6..8]);
+    ///
+    /// let claimer = Schedule::Guided(2).claimer(20, 2);
+    /// let ranges: Vec<_> = claimer.worker(0).collect();
+    /// assert_eq!(ranges, [0..10, 10..15, 15..18, 18..20]);
+    /// ```
+    pub fn claimer(self, len: usize, n_workers: usize) -> Claimer {
+        assert!(n_workers > 0, "no workers to schedule for");
+        match self {
+            Schedule::Static => {}
+            Schedule::StaticChunked(size) | Schedule::Dynamic(size) | Schedule::Guided(size) => {
+                assert!(size > 0, "chunk size must be positive");
+            }
+        }
+
+        Claimer {
+            schedule: self,
+            len,
+            n_workers,
+            cursor: AtomicUsize::new(0),
+        }
+    }
+}
+
+/// The shared state of one scheduled loop over `0..len`.
+///
+/// `Claimer` is `Sync`, so workers on different threads can pull from
+/// the same one.
+#[derive(Debug)]
+pub struct Claimer {
+    schedule: Schedule,
+    len: usize,
+    n_workers: usize,
+    /// The first index not yet claimed, for the schedules that claim
+    /// at run time.
+    cursor: AtomicUsize,
+}
+
+impl Claimer {
+    #[inline]
+    pub fn schedule(&self) -> Schedule {
+        self.schedule
+    }
+
+    /// The number of indices in the loop.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn n_workers(&self) -> usize {
+        self.n_workers
+    }
+
+    /// The ranges that `worker` runs, in increasing order.
+    ///
+    /// For the static schedules these are fixed up front. For the
+    /// others, each call to `next` claims a new range that no other
+    /// worker gets.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `worker >= self.n_workers()`.
+    pub fn worker(&self, worker: usize) -> Claims<'_> {
+        assert!(worker < self.n_workers, "worker out of range");
+
+        let next = match self.schedule {
+            Schedule::Static => worker,
+            Schedule::StaticChunked(size) => worker.saturating_mul(size),
+            Schedule::Dynamic(_) | Schedule::Guided(_) => 0,
+        };
+        Claims {
+            claimer: self,
+            worker,
+            next,
+        }
+    }
+
+    /// Claims the next range for the schedules that claim at run time.
+    fn claim(&self, size_of: impl Fn(usize) -> usize) -> Option<Range<usize>> {
+        let len = self.len;
+        let start = self
+            .cursor
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |start| {
+                (start < len).then(|| start + size_of(len - start).min(len - start))
+            })
+            .ok()?;
+
+        Some(start..start + size_of(len - start).min(len - start))
+    }
+}
+
+/// The ranges one worker runs, from [`Claimer::worker`].
+#[derive(Debug)]
+pub struct Claims<'a> {
+    claimer: &'a Claimer,
+    worker: usize,
+    /// For `Static`, the next part; for `StaticChunked`, the start of
+    /// the next chunk.
+    next: usize,
+}
+
+impl Claims<'_> {
+    #[inline]
+    pub fn worker(&self) -> usize {
+        self.worker
+    }
+}
+
+impl Iterator for Claims<'_> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Claimer {
+            schedule,
+            len,
+            n_workers,
+            ..
+        } = *self.claimer;
+
+        match schedule {
+            Schedule::Static => {
+                let layout = PartsLayout::new(len, n_workers);
+                if self.next >= layout.n_parts() {
+                    return None;
+                }
+                let range = layout.part_range(self.next);
+                self.next = usize::MAX;
+                Some(range)
+            }
+            Schedule::StaticChunked(size) => {
+                let start = self.next;
+                if start >= len {
+                    return None;
+                }
+                let end = start.saturating_add(size).min(len);
+                self.next = start.saturating_add(size.saturating_mul(n_workers));
+                Some(start..end)
+            }
+            Schedule::Dynamic(size) => self.claimer.claim(|_| size),
+            Schedule::Guided(min) => self
+                .claimer
+                .claim(|remaining| remaining.div_ceil(n_workers).max(min)),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let Claimer {
+            schedule,
+            len,
+            n_workers,
+            ..
+        } = *self.claimer;
+
+        match schedule {
+            Schedule::Static => {
+                let n = usize::from(self.next < PartsLayout::new(len, n_workers).n_parts());
+                (n, Some(n))
+            }
+            Schedule::StaticChunked(size) => {
+                let n = if self.next >= len {
+                    0
+                } else {
+                    (len - self.next - 1) / size.saturating_mul(n_workers) + 1
+                };
+                (n, Some(n))
+            }
+            Schedule::Dynamic(size) | Schedule::Guided(size) => {
+                let remaining = len.saturating_sub(self.claimer.cursor.load(Ordering::Relaxed));
+                (0, Some(remaining.div_ceil(size)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{sync::Mutex, thread, vec, vec::Vec};
+
+    const SCHEDULES: [Schedule; 7] = [
+        Schedule::Static,
+        Schedule::StaticChunked(1),
+        Schedule::StaticChunked(3),
+        Schedule::Dynamic(1),
+        Schedule::Dynamic(4),
+        Schedule::Guided(1),
+        Schedule::Guided(3),
+    ];
+
+    fn check_claims(claims: Claims<'_>, ctx: &str) -> Vec<Range<usize>> {
+        let (lower, upper) = claims.size_hint();
+        let ranges: Vec<_> = claims.collect();
+        assert!(
+            lower <= ranges.len(),
+            "{ctx}: size_hint {lower} > {}",
+            ranges.len()
+        );
+        assert!(
+            upper.is_none_or(|u| ranges.len() <= u),
+            "{ctx}: size_hint {upper:?}"
+        );
+        assert!(ranges.iter().all(|r| !r.is_empty()), "{ctx}: empty range");
+        assert!(
+            ranges.windows(2).all(|w| w[0].end <= w[1].start),
+            "{ctx}: out of order"
+        );
+        ranges
+    }
+
+    fn check_cover(ranges: &[Range<usize>], len: usize, ctx: &str) {
+        let mut seen = vec![false; len];
+        for i in ranges.iter().cloned().flatten() {
+            assert!(!seen[i], "{ctx}: {i} claimed twice");
+            seen[i] = true;
+        }
+        assert!(seen.iter().all(|&s| s), "{ctx}: unclaimed indices");
+    }
+
+    #[test]
+    fn every_index_once() {
+        for schedule in SCHEDULES {
+            for n_workers in 1..6 {
+                for len in 0..40 {
+                    let ctx = format!("{schedule:?}, n={n_workers}, len={len}");
+                    let claimer = schedule.claimer(len, n_workers);
+
+                    // Interleave the workers one claim at a time.
+                    let mut workers: Vec<_> = (0..n_workers).map(|w| claimer.worker(w)).collect();
+                    let mut ranges = Vec::new();
+                    while ranges.len() < len {
+                        let before = ranges.len();
+                        ranges.extend(workers.iter_mut().filter_map(Iterator::next));
+                        if ranges.len() == before {
+                            break;
+                        }
+                    }
+                    assert!(workers.iter_mut().all(|w| w.next().is_none()), "{ctx}");
+                    check_cover(&ranges, len, &ctx);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn static_follows_the_layout() {
+        for (len, n) in [(0, 3), (10, 3), (2, 5), (100, 7)] {
+            let claimer = Schedule::Static.claimer(len, n);
+            let layout = PartsLayout::new(len, n);
+            for w in 0..n {
+                let ctx = format!("len={len}, n={n}, w={w}");
+                let ranges = check_claims(claimer.worker(w), &ctx);
+                let expected: Vec<_> = (w < layout.n_parts())
+                    .then(|| layout.part_range(w))
+                    .into_iter()
+                    .collect();
+                assert_eq!(ranges, expected, "{ctx}");
+            }
+        }
+    }
+
+    #[test]
+    fn static_chunked_is_round_robin() {
+        let claimer = Schedule::StaticChunked(3).claimer(20, 3);
+        let ranges: Vec<_> = (0..3)
+            .map(|w| check_claims(claimer.worker(w), ""))
+            .collect();
+        assert_eq!(
+            ranges,
+            [
+                vec![0..3, 9..12, 18..20],
+                vec![3..6, 12..15],
+                vec![6..9, 15..18]
+            ]
+        );
+
+        // Chunks too large to step over without overflow.
+        let claimer = Schedule::StaticChunked(usize::MAX).claimer(usize::MAX, 2);
+        let ranges = check_claims(claimer.worker(0), "");
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0], 0..usize::MAX);
+        assert_eq!(check_claims(claimer.worker(1), ""), []);
+    }
+
+    #[test]
+    fn guided_chunks_shrink() {
+        for (len, n, min) in [(1000, 4, 1), (1000, 3, 10), (17, 2, 4), (5, 8, 1)] {
+            let ctx = format!("len={len}, n={n}, min={min}");
+            let claimer = Schedule::Guided(min).claimer(len, n);
+            let ranges = check_claims(claimer.worker(n - 1), &ctx);
+            check_cover(&ranges, len, &ctx);
+
+            let sizes: Vec<_> = ranges.iter().map(|r| r.len()).collect();
+            assert!(sizes.windows(2).all(|w| w[0] >= w[1]), "{ctx}: {sizes:?}");
+            assert!(
+                sizes[..sizes.len() - 1].iter().all(|&s| s >= min),
+                "{ctx}: {sizes:?}"
+            );
+            assert_eq!(sizes[0], len.div_ceil(n).max(min).min(len), "{ctx}");
+        }
+    }
+
+    #[test]
+    fn dynamic_claims_near_the_end() {
+        let claimer = Schedule::Dynamic(4).claimer(usize::MAX, 2);
+        claimer.cursor.store(usize::MAX - 6, Ordering::Relaxed);
+        let mut worker = claimer.worker(1);
+        assert_eq!(worker.next(), Some(usize::MAX - 6..usize::MAX - 2));
+        assert_eq!(worker.next(), Some(usize::MAX - 2..usize::MAX));
+        assert_eq!(worker.next(), None);
+        assert_eq!(claimer.worker(0).next(), None);
+    }
+
+    #[test]
+    fn threads_share_a_claimer() {
+        for schedule in SCHEDULES {
+            let len = 10_000;
+            let claimer = schedule.claimer(len, 4);
+            let ranges = Mutex::new(Vec::new());
+
+            thread::scope(|s| {
+                for w in 0..4 {
+                    let (claimer, ranges) = (&claimer, &ranges);
+                    s.spawn(move || {
+                        let mine = check_claims(claimer.worker(w), &format!("{schedule:?}"));
+                        ranges.lock().unwrap().extend(mine);
+                    });
+                }
+            });
+
+            check_cover(&ranges.into_inner().unwrap(), len, &format!("{schedule:?}"));
+        }
+    }
+}
@@ -0,0 +1,318 @@
+//! Balanced parts over jagged data, such as a list of batches of uneven
+//! size, where a part may start and end partway through a segment.
+//!
+//! The part lengths are those `seri2_slice::Parts` would give the
+//! segments concatenated into one slice.
+
+use core::marker::PhantomData;
+
+use crate::layout::PartsLayout;
+
+/// Divides the elements of `segments` into a maximum of `n_parts`
+/// parts, as if they were one slice.
+///
+/// Each part is yielded as an iterator over `(segment_index, sub_slice)`
+/// pairs, in order. Sub-slices are never empty, so empty segments are
+/// skipped.
+///
+/// # Panics
+///
+/// Panics if `n_parts` is 0, or if the total length overflows.
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::segments::parts_over_segments;
+///
+/// let batches = [vec![1, 2, 3, 4, 5], vec![], vec![6, 7, 8]];
+/// let parts: Vec<Vec<_>> = parts_over_segments(&batches, 2)
+///     .map(|part| part.collect())
+///     .collect();
+/// assert_eq!(
+///     parts,
+///     [vec![(0, &[1, 2, 3, 4][..])], vec![(0, &[5][..]), (2, &[6, 7, 8][..])]]
+/// );
+/// ```
+///
+/// A `VecDeque` is two segments:
+///
+/// ```
+/// use sheps_chunkerino::segments::parts_over_segments;
+/// use std::collections::VecDeque;
+///
+/// let mut deque: VecDeque<_> = (1..=6).collect();
+/// deque.rotate_left(4);
+/// let (front, back) = deque.as_slices();
+/// let segments = [front, back];
+/// let lens: Vec<usize> = parts_over_segments(&segments, 3)
+///     .map(|part| part.map(|(_, s)| s.len()).sum())
+///     .collect();
+/// assert_eq!(lens, [2, 2, 2]);
+/// ```
+pub fn parts_over_segments<T, S>(segments: &[S], n_parts: usize) -> SegmentParts<'_, T, S>
+where
+    S: AsRef<[T]>,
+{
+    let len = segments
+        .iter()
+        .try_fold(0usize, |len, s| len.checked_add(s.as_ref().len()))
+        .expect("total length overflows usize");
+    let layout = PartsLayout::new(len, n_parts);
+
+    SegmentParts {
+        segments,
+        layout,
+        front_part: 0,
+        back_part: layout.n_parts(),
+        front: Cursor {
+            segment: 0,
+            offset: 0,
+        },
+        back: Cursor {
+            segment: segments.len(),
+            offset: 0,
+        },
+        _marker: PhantomData,
+    }
+}
+
+/// A position within the segments.
+#[derive(Clone, Copy, Debug)]
+struct Cursor {
+    segment: usize,
+    offset: usize,
+}
+
+/// Iterator returned by [`parts_over_segments`].
+pub struct SegmentParts<'s, T, S> {
+    segments: &'s [S],
+    layout: PartsLayout,
+    front_part: usize,
+    back_part: usize,
+    /// Start of the first remaining part.
+    front: Cursor,
+    /// End of the last remaining part.
+    back: Cursor,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<'s, T, S> Iterator for SegmentParts<'s, T, S>
+where
+    S: AsRef<[T]>,
+{
+    type Item = PartSegments<'s, T, S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front_part == self.back_part {
+            return None;
+        }
+
+        let part = PartSegments {
+            segments: self.segments,
+            at: self.front,
+            remaining: self.layout.part_len(self.front_part),
+            _marker: PhantomData,
+        };
+
+        // Walk past the part without handing out its sub-slices.
+        let mut walk = part.clone();
+        while walk.next().is_some() {}
+        self.front = walk.at;
+        self.front_part += 1;
+
+        Some(part)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back_part - self.front_part;
+        (len, Some(len))
+    }
+}
+
+impl<T, S> DoubleEndedIterator for SegmentParts<'_, T, S>
+where
+    S: AsRef<[T]>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front_part == self.back_part {
+            return None;
+        }
+
+        self.back_part -= 1;
+        let len = self.layout.part_len(self.back_part);
+
+        let mut remaining = len;
+        while remaining > 0 {
+            if self.back.offset == 0 {
+                self.back.segment -= 1;
+                self.back.offset = self.segments[self.back.segment].as_ref().len();
+                continue;
+            }
+
+            let take = remaining.min(self.back.offset);
+            self.back.offset -= take;
+            remaining -= take;
+        }
+
+        Some(PartSegments {
+            segments: self.segments,
+            at: self.back,
+            remaining: len,
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// The `(segment_index, sub_slice)` pairs of one part.
+pub struct PartSegments<'s, T, S> {
+    segments: &'s [S],
+    at: Cursor,
+    /// Elements left in the part.
+    remaining: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T, S> Clone for PartSegments<'_, T, S> {
+    fn clone(&self) -> Self {
+        Self {
+            segments: self.segments,
+            at: self.at,
+            remaining: self.remaining,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, S> PartSegments<'_, T, S> {
+    /// The number of elements left in the part, over all segments.
+    #[inline]
+    pub fn elements_len(&self) -> usize {
+        self.remaining
+    }
+}
+
+impl<'s, T: 's, S> Iterator for PartSegments<'s, T, S>
+where
+    S: AsRef<[T]>,
+{
+    type Item = (usize, &'s [T]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        loop {
+            let segment = self.segments[self.at.segment].as_ref();
+            let rest = &segment[self.at.offset..];
+            if rest.is_empty() {
+                self.at = Cursor {
+                    segment: self.at.segment + 1,
+                    offset: 0,
+                };
+                continue;
+            }
+
+            let sub = &rest[..self.remaining.min(rest.len())];
+            let index = self.at.segment;
+            self.at.offset += sub.len();
+            self.remaining -= sub.len();
+
+            return Some((index, sub));
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::from(self.remaining > 0), Some(self.remaining))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{conformance, seri2_slice};
+    use std::{collections::VecDeque, vec, vec::Vec};
+
+    /// Cuts `items` into segments, with empty ones thrown in.
+    fn jagged(items: &[usize], seed: usize) -> Vec<&[usize]> {
+        let mut segments = vec![&items[..0]];
+        let mut rest = items;
+        let mut i = seed;
+        while !rest.is_empty() {
+            i = i.wrapping_mul(31).wrapping_add(7);
+            let len = (i % 5).min(rest.len());
+            let (segment, tail) = rest.split_at(len);
+            segments.push(segment);
+            rest = tail;
+        }
+        segments
+    }
+
+    fn collect<'a>(part: PartSegments<'a, usize, &[usize]>) -> Vec<(usize, &'a [usize])> {
+        part.collect()
+    }
+
+    #[test]
+    fn matches_balanced_parts() {
+        for (items, n) in conformance::cases() {
+            let ctx = format!("len={}, n={n}", items.len());
+            let segments = jagged(&items, items.len() + n);
+            let expected: Vec<_> = seri2_slice::Parts::new(&items, n).collect();
+
+            let parts: Vec<_> = parts_over_segments(&segments, n).map(collect).collect();
+            assert_eq!(parts.len(), expected.len(), "{ctx}");
+
+            for (part, expected) in parts.iter().zip(&expected) {
+                let mut joined = Vec::new();
+                let mut last_index = None;
+                for &(index, sub) in part {
+                    assert!(!sub.is_empty(), "{ctx}");
+                    assert!(last_index < Some(index), "{ctx}");
+                    // The sub-slice really is part of segment `index`.
+                    let segment = segments[index].as_ptr_range();
+                    assert!(segment.contains(&sub.as_ptr()), "{ctx}");
+                    joined.extend_from_slice(sub);
+                    last_index = Some(index);
+                }
+                assert_eq!(joined, *expected, "{ctx}");
+            }
+
+            let mut rev: Vec<_> = parts_over_segments(&segments, n)
+                .rev()
+                .map(collect)
+                .collect();
+            rev.reverse();
+            assert_eq!(rev, parts, "{ctx}");
+        }
+    }
+
+    #[test]
+    fn owned_segments() {
+        let batches = vec![vec![0; 3], vec![1; 1], vec![2; 4]];
+        let lens: Vec<_> = parts_over_segments(&batches, 3)
+            .map(|part| part.elements_len())
+            .collect();
+        assert_eq!(lens, [3, 3, 2]);
+
+        let none: [Vec<u8>; 2] = [vec![], vec![]];
+        assert_eq!(parts_over_segments(&none, 3).count(), 0);
+    }
+
+    #[test]
+    fn vec_deque() {
+        let mut deque: VecDeque<_> = (0..10).collect();
+        deque.rotate_left(7);
+        let (front, back) = deque.as_slices();
+        let segments = [front, back];
+
+        let parts: Vec<_> = parts_over_segments(&segments, 3).map(collect).collect();
+        let items: Vec<_> = deque.iter().copied().collect();
+        let expected: Vec<_> = seri2_slice::Parts::new(&items, 3).collect();
+        for (part, expected) in parts.iter().zip(expected) {
+            let joined: Vec<_> = part.iter().flat_map(|(_, s)| s.iter().copied()).collect();
+            assert_eq!(joined, expected);
+        }
+    }
+}
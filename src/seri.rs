@@ -1,3 +1,5 @@
+use crate::split::split_at_unchecked;
+
 super::ext!();
 
 pub struct Parts<'a, T> {
@@ -48,14 +50,15 @@ impl<'a, T> Iterator for Parts<'a, T> {
             self.times_extra -= 1;
         }
 
-        let (head, tail) = unsafe { slice.split_at_unchecked(chunk_size) };
+        let (head, tail) = unsafe { split_at_unchecked(slice, chunk_size) };
         self.slice = tail;
 
         Some(head)
     }
 }
 
-impl DoubleEndedIterator for Parts<'_, u8> {
+impl<T> DoubleEndedIterator for Parts<'_, T> {
+    #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
         let Self {
             slice,
@@ -67,12 +70,14 @@ impl DoubleEndedIterator for Parts<'_, u8> {
             return None;
         }
 
-        if times_extra > 0 {
+        // The longer chunks are at the front, so only hand one out once
+        // everything behind them is gone.
+        if times_extra > 0 && slice.len() == times_extra * (chunk_size + 1) {
             chunk_size += 1;
             self.times_extra -= 1;
         }
 
-        let (head, tail) = slice.split_at(slice.len() - chunk_size);
+        let (head, tail) = unsafe { split_at_unchecked(slice, slice.len() - chunk_size) };
         self.slice = head;
 
         Some(tail)
@@ -82,47 +87,14 @@ impl DoubleEndedIterator for Parts<'_, u8> {
 #[cfg(test)]
 mod test {
     use super::*;
-    use std::collections::BTreeSet;
 
-    #[test]
-    fn cow_does_it() {
-        // Does not (currently) have empty values.
-        let a = [1];
-        assert_eq!(1, a.parts(100).count());
-
-        // Check our properties across a range of values
-        for item_count in 0..=100 {
-            let items = vec![(); item_count];
-
-            for requested_parts in 1..(items.len() * 2) {
-                let actual_parts = items.parts(requested_parts).count();
-                assert!(actual_parts <= requested_parts);
-
-                let unique_chunk_lens = items
-                    .parts(requested_parts)
-                    .map(|c| c.len())
-                    // .inspect(|x| eprintln!("{x}"))
-                    .collect::<BTreeSet<_>>();
-                assert!(unique_chunk_lens.len() <= 2);
-                // eprintln!("");
-
-                if !unique_chunk_lens.is_empty() {
-                    let mut unique_values = unique_chunk_lens.into_iter();
-                    let short_len = unique_values.next().unwrap();
-                    if let Some(long_len) = unique_values.next() {
-                        assert_eq!(long_len, short_len + 1);
-                    }
-                }
-            }
-        }
-        // panic!()
-    }
+    crate::conformance::conformance_tests!(rev);
 
-    // #[test]
-    // fn rev_test() {
-    //     let a = [0, 1, 2, 3, 4];
+    #[test]
+    fn rev_test() {
+        let a = [0, 1, 2, 3, 4];
 
-    //     let v: Vec<_> = a.parts(2).rev().collect();
-    //     assert_eq!(v, vec![&[2, 3, 4][..], &[0, 1][..]]);
-    // }
+        let v: Vec<_> = a.parts(2).rev().collect();
+        assert_eq!(v, vec![&[3, 4][..], &[0, 1, 2][..]]);
+    }
 }
@@ -1,4 +1,6 @@
-use std::marker::PhantomData;
+use core::marker::PhantomData;
+
+use crate::split::{is_zst, zst_slice};
 
 super::ext!();
 
@@ -8,7 +10,7 @@ pub struct Parts<'a, T> {
     len1: usize,
     chunk_size2: usize,
     len2: usize,
-    _marker: std::marker::PhantomData<&'a T>,
+    _marker: core::marker::PhantomData<&'a T>,
 }
 
 impl<'a, T> Parts<'a, T> {
@@ -51,58 +53,40 @@ impl<'a, T> Iterator for Parts<'a, T> {
     type Item = &'a [T];
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.len1 > 0 {
+        let chunk_size = if self.len1 > 0 {
             self.len1 -= 1;
-            let s = unsafe { std::slice::from_raw_parts(self.ptr, self.chunk_size1) };
-            self.ptr = unsafe { self.ptr.add(self.chunk_size1) };
-            Some(s)
+            self.chunk_size1
         } else if self.len2 > 0 {
             self.len2 -= 1;
-            let s = unsafe { std::slice::from_raw_parts(self.ptr, self.chunk_size2) };
-            self.ptr = unsafe { self.ptr.add(self.chunk_size2) };
-            Some(s)
+            self.chunk_size2
         } else {
-            None
+            return None;
+        };
+
+        if is_zst::<T>() {
+            // Only the length matters, the pointer stays put.
+            return Some(zst_slice(chunk_size));
         }
+
+        let s = unsafe { core::slice::from_raw_parts(self.ptr, chunk_size) };
+        self.ptr = unsafe { self.ptr.add(chunk_size) };
+        Some(s)
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use std::collections::BTreeSet;
-
-    #[test]
-    fn cow_does_it() {
-        // Does not (currently) have empty values.
-        let a = [1];
-        assert_eq!(1, a.parts(100).count());
 
-        // Check our properties across a range of values
-        for item_count in 0..=100 {
-            let items = vec![(); item_count];
+    crate::conformance::conformance_tests!();
 
-            for requested_parts in 1..(items.len() * 2) {
-                let actual_parts = items.parts(requested_parts).count();
-                assert!(actual_parts <= requested_parts);
-
-                let unique_chunk_lens = items
-                    .parts(requested_parts)
-                    .map(|c| c.len())
-                    // .inspect(|x| eprintln!("{x}"))
-                    .collect::<BTreeSet<_>>();
-                assert!(unique_chunk_lens.len() <= 2);
-                // eprintln!("");
+    #[test]
+    fn zst_parts_skip_the_pointer() {
+        let a = [(); usize::MAX];
+        let mut parts = a.parts(3);
 
-                if !unique_chunk_lens.is_empty() {
-                    let mut unique_values = unique_chunk_lens.into_iter();
-                    let short_len = unique_values.next().unwrap();
-                    if let Some(long_len) = unique_values.next() {
-                        assert_eq!(long_len, short_len + 1);
-                    }
-                }
-            }
-        }
-        // panic!()
+        assert_eq!(parts.next().map(<[()]>::len), Some(usize::MAX / 3));
+        assert_eq!(parts.ptr, a.as_ptr());
+        assert!(parts.all(|part| part.as_ptr() == core::ptr::NonNull::dangling().as_ptr()));
     }
 }
@@ -1,3 +1,7 @@
+use core::iter::FusedIterator;
+
+use crate::{layout::PartsLayout, split::split_at_unchecked};
+
 super::ext!();
 
 pub struct Parts<'a, T> {
@@ -11,28 +15,70 @@ pub struct Parts<'a, T> {
 impl<'a, T> Parts<'a, T> {
     #[inline]
     pub fn new(slice: &'a [T], n_parts: usize) -> Self {
-        if slice.len() <= n_parts {
-            // optimize trivial case
-            Self {
-                slice,
-                chunk_size_small: 1,
-                n_small: slice.len(),
-                chunk_size_big: 0,
-                n_big: 0,
-            }
-        } else {
-            let chunk_size = slice.len() / n_parts;
-            let bigger_chunk_count = slice.len() % n_parts;
-
-            Self {
-                slice,
-                chunk_size_small: chunk_size,
-                n_small: n_parts - bigger_chunk_count,
-                chunk_size_big: chunk_size + 1,
-                n_big: bigger_chunk_count,
-            }
+        let layout = PartsLayout::new(slice.len(), n_parts);
+
+        Self {
+            slice,
+            chunk_size_small: layout.small_len(),
+            n_small: layout.n_small(),
+            chunk_size_big: layout.big_len(),
+            n_big: layout.n_big(),
         }
     }
+
+    /// Splits the remaining parts into the first `k` and the rest.
+    ///
+    /// Chaining the two halves yields exactly the parts `self` would
+    /// have yielded. Runs in constant time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is greater than the number of remaining parts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sheps_chunkerino::seri2_slice_flipped::Parts;
+    ///
+    /// let a = [0, 1, 2, 3, 4, 5, 6];
+    /// let (left, right) = Parts::new(&a, 3).split_at_part(1);
+    /// assert_eq!(left.collect::<Vec<_>>(), [&[0, 1][..]]);
+    /// assert_eq!(right.collect::<Vec<_>>(), [&[2, 3][..], &[4, 5, 6][..]]);
+    /// ```
+    pub fn split_at_part(self, k: usize) -> (Self, Self) {
+        assert!(k <= self.len(), "split point out of range");
+
+        // Small parts come first, so the left half takes as many of
+        // them as it can.
+        let left_small = k.min(self.n_small);
+        let left_big = k - left_small;
+        let mid = left_small * self.chunk_size_small + left_big * self.chunk_size_big;
+
+        // SAFETY: The first `k` parts cover `mid` elements, all of which
+        // are in the slice.
+        let (head, tail) = unsafe { split_at_unchecked(self.slice, mid) };
+        let left = Self {
+            slice: head,
+            n_small: left_small,
+            n_big: left_big,
+            ..self
+        };
+        let right = Self {
+            slice: tail,
+            n_small: self.n_small - left_small,
+            n_big: self.n_big - left_big,
+            ..self
+        };
+        (left, right)
+    }
+
+    /// Splits the remaining parts in two, giving the first half
+    /// `self.len() / 2` of them. See [`split_at_part`](Self::split_at_part).
+    #[inline]
+    pub fn split_half(self) -> (Self, Self) {
+        let k = self.len() / 2;
+        self.split_at_part(k)
+    }
 }
 
 impl<'a, T> Iterator for Parts<'a, T> {
@@ -42,10 +88,10 @@ impl<'a, T> Iterator for Parts<'a, T> {
     fn next(&mut self) -> Option<Self::Item> {
         let (head, tail) = if self.n_small > 0 {
             self.n_small -= 1;
-            unsafe { self.slice.split_at_unchecked(self.chunk_size_small) }
+            unsafe { split_at_unchecked(self.slice, self.chunk_size_small) }
         } else if self.n_big > 0 {
             self.n_big -= 1;
-            unsafe { self.slice.split_at_unchecked(self.chunk_size_big) }
+            unsafe { split_at_unchecked(self.slice, self.chunk_size_big) }
         } else {
             return None;
         };
@@ -54,23 +100,28 @@ impl<'a, T> Iterator for Parts<'a, T> {
 
         Some(head)
     }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.n_big + self.n_small;
+        (n, Some(n))
+    }
 }
 
+impl<T> ExactSizeIterator for Parts<'_, T> {}
+
+impl<T> FusedIterator for Parts<'_, T> {}
+
 impl<'a, T> DoubleEndedIterator for Parts<'a, T> {
     #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
-        let (head, tail) = if self.n_small > 0 {
-            self.n_small -= 1;
-            unsafe {
-                self.slice
-                    .split_at_unchecked(self.slice.len() - self.chunk_size_small)
-            }
-        } else if self.n_big > 0 {
+        // Big chunks sit at the end, so they come first from the back.
+        let (head, tail) = if self.n_big > 0 {
             self.n_big -= 1;
-            unsafe {
-                self.slice
-                    .split_at_unchecked(self.slice.len() - self.chunk_size_big)
-            }
+            unsafe { split_at_unchecked(self.slice, self.slice.len() - self.chunk_size_big) }
+        } else if self.n_small > 0 {
+            self.n_small -= 1;
+            unsafe { split_at_unchecked(self.slice, self.slice.len() - self.chunk_size_small) }
         } else {
             return None;
         };
@@ -84,47 +135,14 @@ impl<'a, T> DoubleEndedIterator for Parts<'a, T> {
 #[cfg(test)]
 mod test {
     use super::*;
-    use std::collections::BTreeSet;
 
-    #[test]
-    fn cow_does_it() {
-        // Does not (currently) have empty values.
-        let a = [1];
-        assert_eq!(1, a.parts(100).count());
-
-        // Check our properties across a range of values
-        for item_count in 0..=100 {
-            let items = vec![(); item_count];
-
-            for requested_parts in 1..(items.len() * 2) {
-                let actual_parts = items.parts(requested_parts).count();
-                assert!(actual_parts <= requested_parts);
-
-                let unique_chunk_lens = items
-                    .parts(requested_parts)
-                    .map(|c| c.len())
-                    // .inspect(|x| eprintln!("{x}"))
-                    .collect::<BTreeSet<_>>();
-                assert!(unique_chunk_lens.len() <= 2);
-                // eprintln!("");
-
-                if !unique_chunk_lens.is_empty() {
-                    let mut unique_values = unique_chunk_lens.into_iter();
-                    let short_len = unique_values.next().unwrap();
-                    if let Some(long_len) = unique_values.next() {
-                        assert_eq!(long_len, short_len + 1);
-                    }
-                }
-            }
-        }
-        // panic!()
-    }
+    crate::conformance::conformance_tests!(rev, split);
 
     #[test]
     fn rev_test() {
         let a = [0, 1, 2, 3, 4];
 
         let v: Vec<_> = a.parts(2).rev().collect();
-        assert_eq!(v, vec![&[3, 4][..], &[0, 1, 2][..]]);
+        assert_eq!(v, vec![&[2, 3, 4][..], &[0, 1][..]]);
     }
 }
@@ -0,0 +1,102 @@
+//! `split_at_unchecked` that builds on stable.
+//!
+//! By default the split goes through `from_raw_parts`, the same way
+//! `seri2` walks its pointer. With the `nightly` feature enabled,
+//! core's `<[T]>::split_at_unchecked` is used instead. It was
+//! nightly-only when this module was written, but is stable on the
+//! crate's MSRV, so the feature no longer needs a nightly toolchain.
+//!
+//! Slices of zero-sized types carry no data, so for those the split
+//! only computes lengths and hands out dangling slices, without
+//! touching the pointer at all. The iterators that walk their own
+//! pointer use [`zst_slice`] the same way.
+
+use core::{mem, ptr::NonNull};
+
+/// Divides `slice` into two at `mid` without doing bounds checking.
+///
+/// # Safety
+///
+/// `mid` must be `<= slice.len()`.
+#[inline(always)]
+pub unsafe fn split_at_unchecked<T>(slice: &[T], mid: usize) -> (&[T], &[T]) {
+    if is_zst::<T>() {
+        debug_assert!(mid <= slice.len());
+        return (zst_slice(mid), zst_slice(slice.len() - mid));
+    }
+
+    #[cfg(feature = "nightly")]
+    {
+        slice.split_at_unchecked(mid)
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    {
+        split_at_unchecked_ptr(slice, mid)
+    }
+}
+
+/// Pointer-based variant of [`split_at_unchecked`], always available.
+///
+/// # Safety
+///
+/// `mid` must be `<= slice.len()`.
+#[inline(always)]
+pub unsafe fn split_at_unchecked_ptr<T>(slice: &[T], mid: usize) -> (&[T], &[T]) {
+    let len = slice.len();
+    let ptr = slice.as_ptr();
+
+    debug_assert!(mid <= len);
+
+    (
+        core::slice::from_raw_parts(ptr, mid),
+        core::slice::from_raw_parts(ptr.add(mid), len - mid),
+    )
+}
+
+/// Whether `T` is zero-sized.
+#[inline(always)]
+pub(crate) const fn is_zst<T>() -> bool {
+    mem::size_of::<T>() == 0
+}
+
+/// A slice of `len` ZSTs, built from the length alone.
+///
+/// Only meant for zero-sized `T`; any two such slices of the same
+/// length are interchangeable.
+#[inline(always)]
+pub(crate) fn zst_slice<'a, T>(len: usize) -> &'a [T] {
+    debug_assert!(is_zst::<T>());
+
+    // SAFETY: `T` is zero-sized, so a dangling, aligned pointer is valid
+    // for any number of elements.
+    unsafe { core::slice::from_raw_parts(NonNull::dangling().as_ptr(), len) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn matches_split_at() {
+        let a = [0, 1, 2, 3, 4];
+
+        for mid in 0..=a.len() {
+            assert_eq!(unsafe { split_at_unchecked(&a, mid) }, a.split_at(mid));
+            assert_eq!(unsafe { split_at_unchecked_ptr(&a, mid) }, a.split_at(mid));
+        }
+    }
+
+    #[test]
+    fn zst_at_the_limits() {
+        let a = [(); usize::MAX];
+
+        for mid in [0, 1, usize::MAX / 2, usize::MAX - 1, usize::MAX] {
+            let (head, tail) = unsafe { split_at_unchecked(&a, mid) };
+            assert_eq!((head.len(), tail.len()), (mid, usize::MAX - mid));
+            // Built from the lengths, not by offsetting `a`.
+            assert_eq!(head.as_ptr(), NonNull::dangling().as_ptr());
+            assert_eq!(tail.as_ptr(), NonNull::dangling().as_ptr());
+        }
+    }
+}
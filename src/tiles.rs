@@ -0,0 +1,538 @@
+//! Balanced 2D tiles of a row-major matrix or image stored as a flat
+//! slice, where consecutive rows start `stride` elements apart.
+//!
+//! Rows and columns are each split with the balanced layout of
+//! `seri2_slice::Parts`, so tile heights differ by at most one, as do
+//! tile widths.
+
+use core::{marker::PhantomData, ops::Range, slice};
+
+use crate::layout::PartsLayout;
+
+/// Returns an iterator over the tiles of the `width` by `height` matrix
+/// in `data`, split into a maximum of `rows_n` tiles vertically and
+/// `cols_n` horizontally.
+///
+/// Tiles are yielded in row-major order.
+///
+/// # Panics
+///
+/// Panics if `rows_n` or `cols_n` is 0, if `width > stride`, or if
+/// `data` is too short to hold the matrix.
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::tiles::tiles;
+///
+/// // A 5x3 matrix with one element of padding per row.
+/// #[rustfmt::skip]
+/// let data = [
+///      0,  1,  2,  3,  4, 99,
+///     10, 11, 12, 13, 14, 99,
+///     20, 21, 22, 23, 24,
+/// ];
+///
+/// let mut iter = tiles(&data, 5, 3, 6, 2, 2);
+/// let tile = iter.next().unwrap();
+/// assert_eq!(tile.position(), (0, 0));
+/// assert_eq!((tile.row_range(), tile.col_range()), (0..2, 0..3));
+/// assert_eq!(tile.rows().collect::<Vec<_>>(), [&[0, 1, 2], &[10, 11, 12]]);
+///
+/// let tile = iter.last().unwrap();
+/// assert_eq!(tile.position(), (1, 1));
+/// assert_eq!(tile.rows().collect::<Vec<_>>(), [&[23, 24]]);
+/// ```
+pub fn tiles<T>(
+    data: &[T],
+    width: usize,
+    height: usize,
+    stride: usize,
+    rows_n: usize,
+    cols_n: usize,
+) -> Tiles<'_, T> {
+    let grid = Grid::new(data.len(), width, height, stride, rows_n, cols_n);
+
+    Tiles {
+        data,
+        grid,
+        front: 0,
+        back: grid.n_tiles(),
+    }
+}
+
+/// Like [`tiles`], but the tiles give mutable access to their rows.
+///
+/// The tiles are disjoint, so they can be handed to different threads.
+///
+/// # Panics
+///
+/// Panics under the same conditions as [`tiles`].
+///
+/// # Examples
+///
+/// ```
+/// use sheps_chunkerino::tiles::tiles_mut;
+///
+/// let mut data = [0; 12];
+/// for mut tile in tiles_mut(&mut data, 4, 3, 4, 2, 2) {
+///     let (r, c) = tile.position();
+///     for row in tile.rows_mut() {
+///         row.fill(10 * r + c);
+///     }
+/// }
+/// assert_eq!(data, [0, 0, 1, 1, 0, 0, 1, 1, 10, 10, 11, 11]);
+/// ```
+pub fn tiles_mut<T>(
+    data: &mut [T],
+    width: usize,
+    height: usize,
+    stride: usize,
+    rows_n: usize,
+    cols_n: usize,
+) -> TilesMut<'_, T> {
+    let grid = Grid::new(data.len(), width, height, stride, rows_n, cols_n);
+
+    TilesMut {
+        data: data.as_mut_ptr(),
+        grid,
+        front: 0,
+        back: grid.n_tiles(),
+        _marker: PhantomData,
+    }
+}
+
+/// Where the tiles are, shared by [`Tiles`] and [`TilesMut`].
+#[derive(Clone, Copy, Debug)]
+struct Grid {
+    stride: usize,
+    rows: PartsLayout,
+    cols: PartsLayout,
+}
+
+impl Grid {
+    fn new(
+        len: usize,
+        width: usize,
+        height: usize,
+        stride: usize,
+        rows_n: usize,
+        cols_n: usize,
+    ) -> Self {
+        assert!(width <= stride, "width {width} exceeds stride {stride}");
+
+        let needed = match height.checked_sub(1) {
+            Some(last_row) if width > 0 => last_row
+                .checked_mul(stride)
+                .and_then(|start| start.checked_add(width))
+                .expect("matrix size overflows usize"),
+            _ => 0,
+        };
+        assert!(
+            needed <= len,
+            "a {width}x{height} matrix with stride {stride} needs {needed} elements, got {len}"
+        );
+
+        Self {
+            stride,
+            rows: PartsLayout::new(height, rows_n),
+            cols: PartsLayout::new(width, cols_n),
+        }
+    }
+
+    #[inline]
+    fn n_tiles(&self) -> usize {
+        // Empty if either layout is: a matrix without columns has no tiles.
+        self.rows.n_parts() * self.cols.n_parts()
+    }
+
+    /// The position of tile `i`, its rows and columns, and the offset of
+    /// its first element.
+    #[inline]
+    fn tile(&self, i: usize) -> ((usize, usize), Range<usize>, Range<usize>, usize) {
+        let position = (i / self.cols.n_parts(), i % self.cols.n_parts());
+        let rows = self.rows.part_range(position.0);
+        let cols = self.cols.part_range(position.1);
+        let offset = rows.start * self.stride + cols.start;
+
+        (position, rows, cols, offset)
+    }
+}
+
+/// Iterator returned by [`tiles`].
+pub struct Tiles<'a, T> {
+    data: &'a [T],
+    grid: Grid,
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T> Tiles<'a, T> {
+    #[inline]
+    fn tile(&self, i: usize) -> Tile<'a, T> {
+        let (position, rows, cols, offset) = self.grid.tile(i);
+        let end = offset + (rows.len() - 1) * self.grid.stride + cols.len();
+
+        Tile {
+            data: &self.data[offset..end],
+            stride: self.grid.stride,
+            position,
+            rows,
+            cols,
+        }
+    }
+}
+
+impl<'a, T> Iterator for Tiles<'a, T> {
+    type Item = Tile<'a, T>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        self.front += 1;
+        Some(self.tile(self.front - 1))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for Tiles<'_, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        self.back -= 1;
+        Some(self.tile(self.back))
+    }
+}
+
+impl<T> ExactSizeIterator for Tiles<'_, T> {}
+
+/// A tile of a matrix, yielded by [`tiles`].
+#[derive(Clone, Debug)]
+pub struct Tile<'a, T> {
+    /// From the first element of the first row to the last element of
+    /// the last row.
+    data: &'a [T],
+    stride: usize,
+    position: (usize, usize),
+    rows: Range<usize>,
+    cols: Range<usize>,
+}
+
+impl<'a, T> Tile<'a, T> {
+    /// The `(row, column)` of the tile among the tiles.
+    #[inline]
+    pub fn position(&self) -> (usize, usize) {
+        self.position
+    }
+
+    /// The rows of the matrix the tile covers.
+    #[inline]
+    pub fn row_range(&self) -> Range<usize> {
+        self.rows.clone()
+    }
+
+    /// The columns of the matrix the tile covers.
+    #[inline]
+    pub fn col_range(&self) -> Range<usize> {
+        self.cols.clone()
+    }
+
+    /// Row `r` of the tile, counting from the top of the tile.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `r` is not less than the tile height.
+    #[inline]
+    pub fn row(&self, r: usize) -> &'a [T] {
+        assert!(r < self.rows.len(), "row {r} out of range");
+        let start = r * self.stride;
+        &self.data[start..start + self.cols.len()]
+    }
+
+    /// The rows of the tile, top to bottom.
+    #[inline]
+    pub fn rows(&self) -> impl ExactSizeIterator<Item = &'a [T]> + DoubleEndedIterator + '_ {
+        (0..self.rows.len()).map(|r| self.row(r))
+    }
+}
+
+/// Iterator returned by [`tiles_mut`].
+pub struct TilesMut<'a, T> {
+    data: *mut T,
+    grid: Grid,
+    front: usize,
+    back: usize,
+    _marker: PhantomData<&'a mut [T]>,
+}
+
+// SAFETY: `TilesMut` hands out disjoint `&mut T`s, like `&mut [T]`.
+unsafe impl<T: Send> Send for TilesMut<'_, T> {}
+unsafe impl<T: Sync> Sync for TilesMut<'_, T> {}
+
+impl<'a, T> TilesMut<'a, T> {
+    /// # Safety
+    ///
+    /// Each tile may only be created once.
+    #[inline]
+    unsafe fn tile(&self, i: usize) -> TileMut<'a, T> {
+        let (position, rows, cols, offset) = self.grid.tile(i);
+
+        TileMut {
+            // SAFETY: The grid was checked to fit in the data.
+            data: unsafe { self.data.add(offset) },
+            stride: self.grid.stride,
+            position,
+            rows,
+            cols,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Iterator for TilesMut<'a, T> {
+    type Item = TileMut<'a, T>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        self.front += 1;
+        // SAFETY: Every index between `front` and `back` is used once.
+        Some(unsafe { self.tile(self.front - 1) })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for TilesMut<'_, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        self.back -= 1;
+        // SAFETY: Every index between `front` and `back` is used once.
+        Some(unsafe { self.tile(self.back) })
+    }
+}
+
+impl<T> ExactSizeIterator for TilesMut<'_, T> {}
+
+/// A tile of a matrix with mutable rows, yielded by [`tiles_mut`].
+#[derive(Debug)]
+pub struct TileMut<'a, T> {
+    /// The first element of the first row.
+    data: *mut T,
+    stride: usize,
+    position: (usize, usize),
+    rows: Range<usize>,
+    cols: Range<usize>,
+    _marker: PhantomData<&'a mut [T]>,
+}
+
+// SAFETY: No other tile reaches the elements of this one.
+unsafe impl<T: Send> Send for TileMut<'_, T> {}
+unsafe impl<T: Sync> Sync for TileMut<'_, T> {}
+
+impl<'a, T> TileMut<'a, T> {
+    /// The `(row, column)` of the tile among the tiles.
+    #[inline]
+    pub fn position(&self) -> (usize, usize) {
+        self.position
+    }
+
+    /// The rows of the matrix the tile covers.
+    #[inline]
+    pub fn row_range(&self) -> Range<usize> {
+        self.rows.clone()
+    }
+
+    /// The columns of the matrix the tile covers.
+    #[inline]
+    pub fn col_range(&self) -> Range<usize> {
+        self.cols.clone()
+    }
+
+    /// Row `r` of the tile, counting from the top of the tile.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `r` is not less than the tile height.
+    #[inline]
+    pub fn row(&self, r: usize) -> &[T] {
+        assert!(r < self.rows.len(), "row {r} out of range");
+        // SAFETY: In bounds, and only reachable through this tile.
+        unsafe { slice::from_raw_parts(self.data.add(r * self.stride), self.cols.len()) }
+    }
+
+    /// Row `r` of the tile, mutably.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `r` is not less than the tile height.
+    #[inline]
+    pub fn row_mut(&mut self, r: usize) -> &mut [T] {
+        assert!(r < self.rows.len(), "row {r} out of range");
+        // SAFETY: In bounds, and only reachable through this tile.
+        unsafe { slice::from_raw_parts_mut(self.data.add(r * self.stride), self.cols.len()) }
+    }
+
+    /// The rows of the tile, top to bottom.
+    #[inline]
+    pub fn rows_mut(&mut self) -> impl ExactSizeIterator<Item = &mut [T]> + DoubleEndedIterator {
+        let (data, stride, width) = (self.data, self.stride, self.cols.len());
+        // SAFETY: `width <= stride`, so the rows are disjoint.
+        (0..self.rows.len())
+            .map(move |r| unsafe { slice::from_raw_parts_mut(data.add(r * stride), width) })
+    }
+
+    /// Turns the tile into its rows, top to bottom.
+    #[inline]
+    pub fn into_rows(self) -> impl ExactSizeIterator<Item = &'a mut [T]> + DoubleEndedIterator {
+        let (data, stride, width) = (self.data, self.stride, self.cols.len());
+        // SAFETY: `width <= stride`, so the rows are disjoint.
+        (0..self.rows.len())
+            .map(move |r| unsafe { slice::from_raw_parts_mut(data.add(r * stride), width) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{thread, vec, vec::Vec};
+
+    /// `(row, col)` of every element, with `None` as padding.
+    fn matrix(width: usize, height: usize, stride: usize) -> Vec<Option<(usize, usize)>> {
+        let mut data = vec![None; (height * stride).saturating_sub(stride - width)];
+        for r in 0..height {
+            for c in 0..width {
+                data[r * stride + c] = Some((r, c));
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn tiles_cover_the_matrix() {
+        for height in 0..9 {
+            for width in 0..9 {
+                for stride in [width, width + 3] {
+                    for rows_n in 1..=height + 1 {
+                        for cols_n in 1..=width + 1 {
+                            let ctx = format!(
+                                "{width}x{height}, stride={stride}, {rows_n}x{cols_n} tiles"
+                            );
+                            let data = matrix(width, height, stride);
+                            let rows = PartsLayout::new(height, rows_n);
+                            let cols = PartsLayout::new(width, cols_n);
+
+                            let tiles: Vec<_> =
+                                tiles(&data, width, height, stride, rows_n, cols_n).collect();
+                            assert_eq!(tiles.len(), rows.n_parts() * cols.n_parts(), "{ctx}");
+
+                            let mut seen = vec![0; data.len()];
+                            for (i, tile) in tiles.iter().enumerate() {
+                                let (tr, tc) = tile.position();
+                                assert_eq!(i, tr * cols.n_parts() + tc, "{ctx}");
+                                assert_eq!(tile.row_range(), rows.part_range(tr), "{ctx}");
+                                assert_eq!(tile.col_range(), cols.part_range(tc), "{ctx}");
+
+                                for (dr, row) in tile.rows().enumerate() {
+                                    let r = tile.row_range().start + dr;
+                                    let expected: Vec<_> =
+                                        tile.col_range().map(|c| Some((r, c))).collect();
+                                    assert_eq!(row, expected, "{ctx}");
+                                    for c in tile.col_range() {
+                                        seen[r * stride + c] += 1;
+                                    }
+                                }
+                            }
+                            for (i, elem) in data.iter().enumerate() {
+                                assert_eq!(seen[i], usize::from(elem.is_some()), "{ctx}");
+                            }
+
+                            let mut rev: Vec<_> =
+                                super::tiles(&data, width, height, stride, rows_n, cols_n)
+                                    .rev()
+                                    .map(|t| t.position())
+                                    .collect();
+                            rev.reverse();
+                            let positions: Vec<_> = tiles.iter().map(|t| t.position()).collect();
+                            assert_eq!(rev, positions, "{ctx}");
+
+                            let mut copy = data.clone();
+                            for tile in
+                                tiles_mut(&mut copy, width, height, stride, rows_n, cols_n).rev()
+                            {
+                                for row in tile.into_rows() {
+                                    row.fill(None);
+                                }
+                            }
+                            assert!(copy.iter().all(Option::is_none), "{ctx}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_writers() {
+        let (width, height, stride) = (37, 23, 40);
+        let mut data = vec![0usize; height * stride];
+
+        thread::scope(|s| {
+            for mut tile in tiles_mut(&mut data, width, height, stride, 3, 4) {
+                s.spawn(move || {
+                    let (tr, tc) = tile.position();
+                    for r in 0..tile.row_range().len() {
+                        tile.row_mut(r).fill(1 + tr * 4 + tc);
+                    }
+                });
+            }
+        });
+
+        let rows = PartsLayout::new(height, 3);
+        let cols = PartsLayout::new(width, 4);
+        for r in 0..height {
+            for c in 0..stride {
+                let expected = if c < width {
+                    1 + rows.part_of(r) * 4 + cols.part_of(c)
+                } else {
+                    0
+                };
+                assert_eq!(data[r * stride + c], expected, "({r}, {c})");
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic = "needs 11 elements, got 10"]
+    fn short_data() {
+        let _ = tiles(&[0; 10], 3, 3, 4, 1, 1);
+    }
+
+    #[test]
+    #[should_panic = "width 5 exceeds stride 4"]
+    fn narrow_stride() {
+        let _ = tiles(&[0; 20], 5, 4, 4, 1, 1);
+    }
+}
@@ -0,0 +1,297 @@
+//! Balanced parts of a `VecDeque`, without `make_contiguous()`.
+//!
+//! A part that crosses the wrap point of the ring buffer is yielded as
+//! two slices, like [`VecDeque::as_slices`] does for the whole deque.
+
+use alloc::collections::VecDeque;
+use core::mem;
+
+use crate::layout::PartsLayout;
+
+pub trait VecDequePartsExt<T> {
+    /// Returns an iterator that divides the deque into a maximum of
+    /// `n_parts` parts, in the layout `seri2_slice::Parts` uses.
+    ///
+    /// Each part is a pair of slices, the first of which is never
+    /// empty. The second is only non-empty for the part crossing the
+    /// wrap point.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n_parts` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sheps_chunkerino::vec_deque::VecDequePartsExt;
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut deque = VecDeque::with_capacity(5);
+    /// deque.extend([2, 3, 4]);
+    /// deque.push_front(1);
+    /// deque.push_front(0);
+    ///
+    /// let parts: Vec<Vec<_>> = deque
+    ///     .parts(2)
+    ///     .map(|(a, b)| a.iter().chain(b).copied().collect())
+    ///     .collect();
+    /// assert_eq!(parts, [vec![0, 1, 2], vec![3, 4]]);
+    /// ```
+    fn parts(&self, n_parts: usize) -> Parts<'_, T>;
+
+    /// Like [`parts`](VecDequePartsExt::parts), but with mutable slices.
+    fn parts_mut(&mut self, n_parts: usize) -> PartsMut<'_, T>;
+}
+
+impl<T> VecDequePartsExt<T> for VecDeque<T> {
+    #[inline]
+    fn parts(&self, n_parts: usize) -> Parts<'_, T> {
+        let (head, tail) = self.as_slices();
+        let layout = PartsLayout::new(self.len(), n_parts);
+
+        Parts {
+            head,
+            tail,
+            layout,
+            front: 0,
+            back: layout.n_parts(),
+        }
+    }
+
+    #[inline]
+    fn parts_mut(&mut self, n_parts: usize) -> PartsMut<'_, T> {
+        let layout = PartsLayout::new(self.len(), n_parts);
+        let (head, tail) = self.as_mut_slices();
+
+        PartsMut {
+            head,
+            tail,
+            layout,
+            front: 0,
+            back: layout.n_parts(),
+        }
+    }
+}
+
+/// Iterator returned by [`VecDequePartsExt::parts`].
+pub struct Parts<'a, T> {
+    /// The remaining elements are `head` followed by `tail`.
+    head: &'a [T],
+    tail: &'a [T],
+    layout: PartsLayout,
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T> Iterator for Parts<'a, T> {
+    type Item = (&'a [T], &'a [T]);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        let len = self.layout.part_len(self.front);
+        self.front += 1;
+
+        if self.head.is_empty() {
+            self.head = mem::take(&mut self.tail);
+        }
+
+        if len <= self.head.len() {
+            let (part, rest) = self.head.split_at(len);
+            self.head = rest;
+            Some((part, &[]))
+        } else {
+            let first = mem::take(&mut self.head);
+            let (second, rest) = self.tail.split_at(len - first.len());
+            self.tail = rest;
+            Some((first, second))
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for Parts<'_, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        self.back -= 1;
+        let len = self.layout.part_len(self.back);
+
+        if self.tail.is_empty() {
+            self.tail = mem::take(&mut self.head);
+        }
+
+        if len <= self.tail.len() {
+            let (rest, part) = self.tail.split_at(self.tail.len() - len);
+            self.tail = rest;
+            Some((part, &[]))
+        } else {
+            let second = mem::take(&mut self.tail);
+            let (rest, first) = self.head.split_at(self.head.len() - (len - second.len()));
+            self.head = rest;
+            Some((first, second))
+        }
+    }
+}
+
+/// Iterator returned by [`VecDequePartsExt::parts_mut`].
+pub struct PartsMut<'a, T> {
+    /// The remaining elements are `head` followed by `tail`.
+    head: &'a mut [T],
+    tail: &'a mut [T],
+    layout: PartsLayout,
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T> Iterator for PartsMut<'a, T> {
+    type Item = (&'a mut [T], &'a mut [T]);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        let len = self.layout.part_len(self.front);
+        self.front += 1;
+
+        if self.head.is_empty() {
+            self.head = mem::take(&mut self.tail);
+        }
+
+        if len <= self.head.len() {
+            let (part, rest) = mem::take(&mut self.head).split_at_mut(len);
+            self.head = rest;
+            Some((part, &mut []))
+        } else {
+            let first = mem::take(&mut self.head);
+            let (second, rest) = mem::take(&mut self.tail).split_at_mut(len - first.len());
+            self.tail = rest;
+            Some((first, second))
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for PartsMut<'_, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        self.back -= 1;
+        let len = self.layout.part_len(self.back);
+
+        if self.tail.is_empty() {
+            self.tail = mem::take(&mut self.head);
+        }
+
+        if len <= self.tail.len() {
+            let mid = self.tail.len() - len;
+            let (rest, part) = mem::take(&mut self.tail).split_at_mut(mid);
+            self.tail = rest;
+            Some((part, &mut []))
+        } else {
+            let second = mem::take(&mut self.tail);
+            let mid = self.head.len() - (len - second.len());
+            let (rest, first) = mem::take(&mut self.head).split_at_mut(mid);
+            self.head = rest;
+            Some((first, second))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{conformance, seri2_slice};
+    use std::vec::Vec;
+
+    /// A deque holding `items` that wraps after `wrap` elements, when
+    /// `wrap < items.len()`.
+    fn wrapped(items: &[usize], wrap: usize) -> VecDeque<usize> {
+        let mut deque = VecDeque::with_capacity(items.len());
+        let wrap = wrap.min(items.len());
+        deque.extend(&items[wrap..]);
+        for &item in items[..wrap].iter().rev() {
+            deque.push_front(item);
+        }
+        assert_eq!(deque, items);
+        deque
+    }
+
+    #[test]
+    fn matches_balanced_parts() {
+        for (items, n) in conformance::cases() {
+            let expected: Vec<_> = seri2_slice::Parts::new(&items, n).collect();
+
+            for wrap in [0, 1, items.len() / 2, items.len().saturating_sub(1)] {
+                let ctx = format!("len={}, n={n}, wrap={wrap}", items.len());
+                let mut deque = wrapped(&items, wrap);
+
+                let parts: Vec<_> = deque.parts(n).collect();
+                assert_eq!(parts.len(), expected.len(), "{ctx}");
+                for (&(a, b), expected) in parts.iter().zip(&expected) {
+                    assert!(!a.is_empty(), "{ctx}");
+                    assert_eq!([a, b].concat(), *expected, "{ctx}");
+                }
+                assert!(parts.iter().filter(|(_, b)| !b.is_empty()).count() <= 1);
+
+                let mut rev: Vec<_> = deque.parts(n).rev().collect();
+                rev.reverse();
+                assert_eq!(rev, parts, "{ctx}");
+
+                for (i, (a, b)) in deque.parts_mut(n).rev().enumerate() {
+                    assert!(!a.is_empty(), "{ctx}");
+                    a.iter_mut().chain(b).for_each(|x| *x = i);
+                }
+                let mut owners: Vec<_> = deque.iter().copied().collect();
+                owners.reverse();
+                let runs: Vec<_> = owners.chunk_by(|x, y| x == y).map(<[_]>::len).collect();
+                let mut lens: Vec<_> = expected.iter().map(|p| p.len()).collect();
+                lens.reverse();
+                assert_eq!(runs, lens, "{ctx}");
+            }
+        }
+    }
+
+    #[test]
+    fn mixed_ends() {
+        let mut deque = wrapped(&[0, 1, 2, 3, 4, 5, 6], 3);
+        assert_eq!(deque.as_slices(), (&[0, 1, 2][..], &[3, 4, 5, 6][..]));
+
+        let mut parts = deque.parts_mut(3);
+        let (a, b) = parts.next_back().unwrap();
+        assert_eq!((&*a, &*b), (&[5, 6][..], &[][..]));
+        let (a, b) = parts.next().unwrap();
+        assert_eq!((&*a, &*b), (&[0, 1, 2][..], &[][..]));
+        let (a, b) = parts.next().unwrap();
+        assert_eq!((&*a, &*b), (&[3, 4][..], &[][..]));
+        assert!(parts.next().is_none());
+        assert!(parts.next_back().is_none());
+
+        let parts: Vec<_> = deque.parts(2).collect();
+        assert_eq!(
+            parts,
+            [(&[0, 1, 2][..], &[3][..]), (&[4, 5, 6][..], &[][..])]
+        );
+    }
+}
@@ -0,0 +1,261 @@
+//! Randomized differential testing of every `Parts` implementation
+//! against a simple, safe reference model.
+//!
+//! Cases come from a seeded PRNG, so a failure can be reproduced with
+//! `DIFFERENTIAL_SEED=<seed>`. `DIFFERENTIAL_CASES` sets the number of
+//! cases per element type.
+
+use std::{env, ops::Range};
+
+use sheps_chunkerino::{
+    double_chunk, on_demand, precomputed, seri, seri2, seri2_flipped, seri2_slice,
+    seri2_slice_flipped,
+};
+
+/// How many parts are compared from each end when a case has too many
+/// parts to walk through all of them.
+const EDGE_PARTS: usize = 64;
+
+/// Cases with at most this many parts are compared in full.
+const FULL_PARTS: usize = 4096;
+
+/// Longest slice used for element types that occupy memory.
+const MAX_SIZED_LEN: usize = 1 << 16;
+
+/// SplitMix64, which is plenty for picking test cases.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        if bound == 0 {
+            0
+        } else {
+            (self.next_u64() % bound as u64) as usize
+        }
+    }
+
+    /// A length up to `max`, biased towards the edges.
+    fn len(&mut self, max: usize) -> usize {
+        match self.below(6) {
+            0 => self.below(4),
+            1 => max - self.below(4).min(max),
+            2 => self.below(64),
+            3 => 1 << self.below(max.ilog2() as usize + 1),
+            _ => self.below(max) + 1,
+        }
+    }
+
+    /// A part count for a slice of length `len`, biased towards the
+    /// edges the implementations special-case.
+    fn n(&mut self, len: usize) -> usize {
+        let n = match self.below(8) {
+            0 => 1,
+            1 => len,
+            2 => len.saturating_add(1),
+            3 => len.saturating_sub(1),
+            4 => usize::MAX - self.below(4),
+            5 => self.below(64),
+            6 => self.len(len.max(1)),
+            _ => self.len(usize::MAX),
+        };
+        n.max(1)
+    }
+}
+
+/// Where the longer parts go.
+#[derive(Clone, Copy, Debug)]
+enum Order {
+    BigFirst,
+    SmallFirst,
+}
+
+/// The safe reference model: the range part `i` covers.
+fn model_part(len: usize, n: usize, order: Order, i: usize) -> Range<usize> {
+    let small = len / n;
+    let n_big = len % n;
+
+    let start = match order {
+        // With fewer items than parts, every non-empty part is "big".
+        Order::SmallFirst if small > 0 => {
+            let n_small = n - n_big;
+            if i < n_small {
+                i * small
+            } else {
+                n_small * small + (i - n_small) * (small + 1)
+            }
+        }
+        _ => i * small + i.min(n_big),
+    };
+    let part_len = match order {
+        Order::SmallFirst if small > 0 => small + usize::from(i >= n - n_big),
+        _ => small + usize::from(i < n_big),
+    };
+
+    start..start + part_len
+}
+
+fn model_count(len: usize, n: usize) -> usize {
+    len.min(n)
+}
+
+/// Compares `actual`, the `i`-th part of `slice`, against the model.
+fn check_part<T>(slice: &[T], n: usize, order: Order, i: usize, actual: &[T], ctx: &str) {
+    let expected = model_part(slice.len(), n, order, i);
+
+    assert_eq!(actual.len(), expected.len(), "{ctx}: length of part {i}");
+
+    if std::mem::size_of::<T>() != 0 {
+        let offset =
+            (actual.as_ptr() as usize - slice.as_ptr() as usize) / std::mem::size_of::<T>();
+        assert_eq!(offset, expected.start, "{ctx}: start of part {i}");
+    }
+}
+
+fn check_forward<'a, T: 'a>(
+    slice: &'a [T],
+    n: usize,
+    order: Order,
+    mut parts: impl Iterator<Item = &'a [T]>,
+    ctx: &str,
+) {
+    let count = model_count(slice.len(), n);
+    let limit = if count <= FULL_PARTS {
+        count
+    } else {
+        EDGE_PARTS
+    };
+
+    for i in 0..limit {
+        let part = parts
+            .next()
+            .unwrap_or_else(|| panic!("{ctx}: ran out after {i} parts"));
+        check_part(slice, n, order, i, part, ctx);
+    }
+
+    if count <= FULL_PARTS {
+        assert!(parts.next().is_none(), "{ctx}: more than {count} parts");
+    }
+}
+
+fn check_backward<'a, T: 'a>(
+    slice: &'a [T],
+    n: usize,
+    order: Order,
+    mut parts: impl DoubleEndedIterator<Item = &'a [T]>,
+    ctx: &str,
+) {
+    let count = model_count(slice.len(), n);
+    let limit = count.min(EDGE_PARTS);
+
+    for i in (count - limit..count).rev() {
+        let part = parts
+            .next_back()
+            .unwrap_or_else(|| panic!("{ctx}: ran out at part {i} from the back"));
+        check_part(slice, n, order, i, part, ctx);
+    }
+}
+
+fn check_all<T>(slice: &[T], n: usize, case: usize, seed: u64) {
+    macro_rules! forward {
+        ($($module:ident: $order:ident),* $(,)?) => {$(
+            let ctx = format!(
+                "{}: len={}, n={n} (case {case}, seed {seed})",
+                stringify!($module),
+                slice.len(),
+            );
+            check_forward(slice, n, Order::$order, $module::Parts::new(slice, n), &ctx);
+        )*};
+    }
+    macro_rules! backward {
+        ($($module:ident: $order:ident),* $(,)?) => {$(
+            let ctx = format!(
+                "{} (rev): len={}, n={n} (case {case}, seed {seed})",
+                stringify!($module),
+                slice.len(),
+            );
+            check_backward(slice, n, Order::$order, $module::Parts::new(slice, n), &ctx);
+        )*};
+    }
+
+    forward!(
+        seri: BigFirst,
+        seri2: BigFirst,
+        seri2_flipped: SmallFirst,
+        seri2_slice: BigFirst,
+        seri2_slice_flipped: SmallFirst,
+        precomputed: BigFirst,
+        double_chunk: BigFirst,
+        on_demand: BigFirst,
+    );
+    backward!(
+        seri: BigFirst,
+        seri2_slice: BigFirst,
+        seri2_slice_flipped: SmallFirst,
+    );
+}
+
+fn seed() -> u64 {
+    env::var("DIFFERENTIAL_SEED")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0x5eed_c0ff_ee00_0001)
+}
+
+fn cases() -> usize {
+    env::var("DIFFERENTIAL_CASES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(2000)
+}
+
+#[test]
+fn model_matches_known_layouts() {
+    let parts = |order| {
+        (0..model_count(5, 2))
+            .map(|i| model_part(5, 2, order, i))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(parts(Order::BigFirst), [0..3, 3..5]);
+    assert_eq!(parts(Order::SmallFirst), [0..2, 2..5]);
+
+    assert_eq!(model_count(3, 5), 3);
+    assert_eq!(model_part(3, 5, Order::SmallFirst, 2), 2..3);
+}
+
+#[test]
+fn sized_elements() {
+    let seed = seed();
+    let mut rng = Rng(seed);
+    let buf: Vec<u32> = (0..MAX_SIZED_LEN as u32).collect();
+
+    for case in 0..cases() {
+        let len = rng.len(MAX_SIZED_LEN);
+        let n = rng.n(len);
+        let start = rng.below(MAX_SIZED_LEN - len + 1);
+
+        check_all(&buf[start..start + len], n, case, seed);
+    }
+}
+
+#[test]
+fn zero_sized_elements() {
+    let seed = seed();
+    let mut rng = Rng(seed ^ 0x2e0);
+    // A ZST slice of any length needs no memory.
+    let buf = [(); usize::MAX];
+
+    for case in 0..cases() {
+        let len = rng.len(usize::MAX);
+        let n = rng.n(len);
+
+        check_all(&buf[..len], n, case, seed);
+    }
+}
@@ -0,0 +1,84 @@
+//! Builds the library as `#![no_std]`, so anything reaching for `std`
+//! outside of the `std`/`alloc` features fails the test suite.
+//!
+//! The host build only catches `std` paths through the `no_std`
+//! attribute, since `std` is still there to link against. When the
+//! bare-metal target below is installed, the library is also built for
+//! it, which catches dependencies that pull in `std` as well.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// A target without `std`. Install it with
+/// `rustup target add thumbv7em-none-eabihf`.
+const BARE_METAL_TARGET: &str = "thumbv7em-none-eabihf";
+
+fn build_lib(features: &str, target: Option<&str>) {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("no_std");
+
+    let mut command = Command::new(env!("CARGO"));
+    command
+        .arg("build")
+        .arg("--lib")
+        .arg("--no-default-features")
+        .args(["--features", features])
+        .arg("--manifest-path")
+        .arg(manifest_dir.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(target_dir);
+    if let Some(target) = target {
+        command.args(["--target", target]);
+    }
+    let output = command.output().unwrap();
+
+    assert!(
+        output.status.success(),
+        "no_std build with features {features:?} for {} failed:\n{}",
+        target.unwrap_or("the host"),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// The bare-metal target, if its `core` is installed for the toolchain
+/// running the tests.
+fn bare_metal_target() -> Option<&'static str> {
+    let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = Command::new(rustc)
+        .args(["--print", "sysroot"])
+        .output()
+        .ok()?;
+    let sysroot = PathBuf::from(String::from_utf8(output.stdout).ok()?.trim());
+
+    if sysroot.join("lib/rustlib").join(BARE_METAL_TARGET).is_dir() {
+        Some(BARE_METAL_TARGET)
+    } else {
+        eprintln!("skipping the {BARE_METAL_TARGET} build: target not installed");
+        None
+    }
+}
+
+fn build_everywhere(features: &str) {
+    build_lib(features, None);
+    if let Some(target) = bare_metal_target() {
+        build_lib(features, Some(target));
+    }
+}
+
+#[test]
+fn core_only() {
+    build_everywhere("");
+}
+
+#[test]
+fn alloc_only() {
+    build_everywhere("alloc");
+}
+
+#[test]
+fn serde() {
+    build_everywhere("serde");
+    build_everywhere("alloc serde");
+}